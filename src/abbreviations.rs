@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+
+/// Characters that expand an abbreviation when typed right after it (Tab is handled separately)
+pub const TRIGGERS: &[&str] = &[" ", "\u{3000}"];
+
+/// The abbreviation at the end of `prefix` (text before the cursor), longest first
+/// It must not be glued to a preceding ASCII word, so `;rv` expands after Japanese text but `foo;rv` doesn't
+pub fn find<'a>(prefix: &str, abbreviations: &'a BTreeMap<String, String>) -> Option<(&'a str, &'a str)> {
+    abbreviations
+        .iter()
+        .filter(|(key, _)| !key.is_empty() && prefix.ends_with(key.as_str()))
+        .filter(|(key, _)| {
+            let before = &prefix[..prefix.len() - key.len()];
+            !before.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .max_by_key(|(key, _)| key.len())
+        .map(|(key, expansion)| (key.as_str(), expansion.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let abbreviations = BTreeMap::from([
+            (";rv".to_string(), "以下のコードをレビューしてください。".to_string()),
+            (";rvs".to_string(), "セキュリティの観点でレビューしてください。".to_string()),
+            ("tbd".to_string(), "未定".to_string()),
+        ]);
+        assert_eq!(find("これを;rv", &abbreviations).map(|(k, _)| k), Some(";rv"));
+        assert_eq!(find(";rvs", &abbreviations).map(|(k, _)| k), Some(";rvs"));
+        assert_eq!(find("tbd", &abbreviations).map(|(k, _)| k), Some("tbd"));
+        assert!(find("outbd", &abbreviations).is_none());
+        assert!(find(";r", &abbreviations).is_none());
+    }
+}
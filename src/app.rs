@@ -1,10 +1,14 @@
 use iced::keyboard::{self, Key};
-use iced::widget::{button, column, container, row, text, text_editor, Id};
+use iced::widget::{button, checkbox, column, container, rich_text, row, scrollable, span, text, text_editor, text_input, Id};
 use iced::widget::operation::focus;
 use iced::{event, Element, Event, Font, Length, Size, Subscription, Task};
 use iced::{Background, Border, Color, Theme};
 use iced::window;
-use std::sync::{LazyLock, OnceLock};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, OnceLock, RwLock};
+use std::time::Duration;
 
 /// Adjectives for random name generation (Docker-style)
 const ADJECTIVES: &[&str] = &[
@@ -51,11 +55,34 @@ fn get_accent_color(hwnd: isize) -> Color {
 /// Static ID for the text editor (for programmatic focus)
 static EDITOR_ID: LazyLock<Id> = LazyLock::new(Id::unique);
 
+/// Static ID for the search box of the snippet and file pickers
+static PICKER_SEARCH_ID: LazyLock<Id> = LazyLock::new(Id::unique);
+
+use crate::abbreviations;
 use crate::clipboard_utils;
+use crate::commands::{self, SlashCommand};
+use crate::completion::{self, Completion, CompletionItem};
+use crate::config::{self, Config};
+use crate::files;
+use crate::git::{self, GitContext};
 use crate::hook;
 use crate::hotkey;
-use crate::terminal;
+use crate::identifiers::{self, IdentifierCounts};
+use crate::ipc;
+use crate::lint::{self, Issue, Rule};
+use crate::paste::{self, PasteTransform};
+use crate::paths;
+use crate::punctuation;
+use crate::queue::PromptQueue;
+use crate::romaji::{self, Preedit};
+use crate::spell::{self, Dictionary};
+use crate::statusline;
+use crate::words;
+use crate::snippets::{self, Snippet};
+use crate::terminal::{self, ForwardKey};
 use crate::logger;
+use crate::markdown;
+use crate::normalize;
 
 /// Configuration for resident mode (stored globally using OnceLock for thread safety)
 static RESIDENT_CONFIG: OnceLock<ResidentConfigData> = OnceLock::new();
@@ -65,24 +92,219 @@ struct ResidentConfigData {
     terminal_hwnd: Option<isize>,
     window_title: String,
     accent_color: Color,
+    cwd: Option<String>,
+    transcript: Option<String>,
+    /// Registry entry (None if the IPC listener could not be bound)
+    instance: Option<ipc::InstanceInfo>,
 }
 
 /// Configuration for resident mode
 pub struct ResidentConfig {
     pub terminal_hwnd: Option<isize>,
+    pub label: Option<String>,
+    pub session: Option<String>,
+    pub cwd: Option<String>,
+    pub transcript: Option<String>,
+}
+
+/// Delay before sending a queued prompt after Stop, so Claude is back at its prompt
+const QUEUE_SEND_DELAY: Duration = Duration::from_millis(500);
+
+/// Border flash on notification: number of on/off toggles and their interval
+const FLASH_TOGGLES: u8 = 8;
+const FLASH_INTERVAL: Duration = Duration::from_millis(300);
+
+/// What a search picker lists
+enum PickerKind {
+    /// Ctrl+T: insert a snippet
+    Snippets(Vec<Snippet>),
+    /// Ctrl+O: insert an excerpt of a project file
+    Files,
+}
+
+/// Search picker: query and matching entries (`insert` holds the snippet name or file path)
+struct Picker {
+    kind: PickerKind,
+    query: String,
+    matches: Option<Completion>,
+}
+
+/// File excerpt being prepared (from the file picker or a dropped file)
+struct Excerpt {
+    path: PathBuf,
+    /// Path shown and inserted (relative to the session cwd when inside it)
+    display: String,
+    content: Arc<files::FileContent>,
+    start: String,
+    end: String,
+}
+
+/// Colors the ranges flagged by the proofreading rules (settings: ranges and rules per line)
+/// iced's highlight format has no underline, so the rule picks the color instead
+struct LintHighlighter {
+    marks: Vec<Vec<(Range<usize>, Rule)>>,
+    current_line: usize,
+}
+
+impl iced::advanced::text::Highlighter for LintHighlighter {
+    type Settings = Vec<Vec<(Range<usize>, Rule)>>;
+    type Highlight = Rule;
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, Rule)>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            marks: settings.clone(),
+            current_line: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.marks = new_settings.clone();
+        self.current_line = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, _line: &str) -> Self::Iterator<'_> {
+        let marks = self.marks.get(self.current_line).cloned().unwrap_or_default();
+        self.current_line += 1;
+        marks.into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+/// Cleaned-up paste waiting for confirmation, with the transforms chosen for it
+struct PastePreview {
+    original: String,
+    enabled: Vec<PasteTransform>,
+    cleaned: String,
+    /// Markdown converted from HTML: only its code blocks are cleaned
+    markdown: bool,
+}
+
+/// Apply paste cleanups to plain text, or to the code blocks of converted Markdown
+fn clean_paste(text: &str, enabled: &[PasteTransform], markdown: bool) -> String {
+    if markdown {
+        paste::apply_to_fences(text, enabled)
+    } else {
+        paste::apply(text, enabled)
+    }
+}
+
+/// Placeholders of the last inserted snippet that Tab still visits
+struct SnippetSession {
+    /// Placeholder being edited, as inserted (before the user typed over it)
+    current: (text_editor::Position, text_editor::Position),
+    /// Remaining placeholders as (start, end), in visiting order
+    remaining: Vec<(text_editor::Position, text_editor::Position)>,
+}
+
+/// "Insert git context" panel (Ctrl+G); `hunks` is set while picking hunks
+struct GitPanel {
+    hunks: Option<Vec<(git::Hunk, bool)>>,
+}
+
+/// The last abbreviation expansion, revertible with Ctrl+Z until the next edit
+struct Expansion {
+    /// What was typed (abbreviation plus trigger)
+    typed: String,
+    inserted: String,
 }
 
 /// The main application state for resident mode
 pub struct ResidentClaudeInput {
     content: text_editor::Content,
     status_message: Option<String>,
+    queue: PromptQueue,
+    instance: Option<ipc::InstanceInfo>,
+    /// Stop hooks received so far (batch mode waits for this to change)
+    stop_count: u64,
+    transcript_path: Option<String>,
+    /// Latest notification from Claude (shown as a banner until dismissed)
+    notification: Option<String>,
+    /// Remaining border flash toggles and whether the flash is currently lit
+    flash_remaining: u8,
+    flash_on: bool,
+    /// Latest info from the statusLine command (footer)
+    status_line: Option<ipc::StatusLineInfo>,
+    /// Question from Claude (MCP ask_user): the next submit answers it instead of pasting
+    pending_question: Option<(String, ipc::Reply)>,
+    /// Show the key-forwarding toolbar (Ctrl+K)
+    forwarding: bool,
+    /// Quick action waiting for confirmation (index into config.quick_actions)
+    confirming_action: Option<usize>,
+    config: Config,
+    /// Slash commands offered when the prompt starts with `/`
+    commands: Vec<SlashCommand>,
+    /// Autocomplete popup for the token before the cursor
+    completion: Option<Completion>,
+    /// Project files for the `@` picker (relisted in the background on each new `@`)
+    files: Arc<Vec<String>>,
+    /// Identifiers from the project's source files, kept up to date by a background thread
+    identifiers: Arc<RwLock<IdentifierCounts>>,
+    /// Hunspell dictionary for the spellchecker (empty until loaded, or if there is none)
+    dictionary: Arc<OnceLock<Dictionary>>,
+    /// Words from the project's identifiers that the spellchecker accepts, with the identifier
+    /// count they were harvested at
+    project_words: (usize, HashSet<String>),
+    /// Proofreading and spelling issues, with the text they were found in and whether the
+    /// dictionary had loaded (recomputed after an update changes either)
+    issues: Vec<Issue>,
+    issues_source: (String, bool),
+    /// Built-in romaji input (Ctrl+J) and the text being composed with it
+    romaji_mode: romaji::Mode,
+    preedit: Preedit,
+    picker: Option<Picker>,
+    excerpt: Option<Excerpt>,
+    paste_preview: Option<PastePreview>,
+    snippet_session: Option<SnippetSession>,
+    last_expansion: Option<Expansion>,
+    git_panel: Option<GitPanel>,
 }
 
 impl Default for ResidentClaudeInput {
     fn default() -> Self {
+        let config = get_config();
+        let settings = config::load_for_project(session_cwd().as_deref());
+        let dictionary = spell::spawn_loader(settings.spellcheck.dictionary_path());
         Self {
             content: text_editor::Content::new(),
             status_message: None,
+            queue: PromptQueue::load(config.and_then(|c| c.cwd.as_deref())),
+            instance: config.and_then(|c| c.instance.clone()),
+            stop_count: 0,
+            transcript_path: config.and_then(|c| c.transcript.clone()),
+            notification: None,
+            flash_remaining: 0,
+            flash_on: false,
+            status_line: None,
+            pending_question: None,
+            forwarding: false,
+            confirming_action: None,
+            config: settings,
+            commands: commands::load_commands(session_cwd().as_deref()),
+            completion: None,
+            files: Arc::default(),
+            identifiers: session_cwd()
+                .map(|cwd| identifiers::spawn_indexer(cwd.into()))
+                .unwrap_or_default(),
+            dictionary,
+            project_words: (0, HashSet::new()),
+            issues: Vec::new(),
+            issues_source: (String::new(), false),
+            romaji_mode: romaji::Mode::Off,
+            preedit: Preedit::default(),
+            picker: None,
+            excerpt: None,
+            paste_preview: None,
+            snippet_session: None,
+            last_expansion: None,
+            git_panel: None,
         }
     }
 }
@@ -96,6 +318,63 @@ fn resident_theme(_state: &ResidentClaudeInput) -> Theme {
 pub enum ResidentMessage {
     EditorAction(text_editor::Action),
     Submit,        // Send via direct paste (Ctrl+V)
+    Enqueue,       // Add to the prompt queue instead of sending
+    QueueMoveUp(usize),
+    QueueMoveDown(usize),
+    QueueRemove(usize),
+    SendQueued,    // Send the next queued prompt (after Stop)
+    DeliverDeferred(String), // Text from a hook, sent once the hook has exited
+    DismissNotification,
+    DismissQuestion,
+    QuickAction(usize),   // Toolbar button pressed (may ask for confirmation)
+    RunQuickAction(usize), // Send the quick action
+    CancelQuickAction,
+    CompletionMove(isize),
+    AcceptCompletion(usize),
+    CloseCompletion,
+    FilesLoaded(Arc<Vec<String>>),
+    ExcerptLoaded(PathBuf, Result<Arc<files::FileContent>, String>),
+    OpenSnippets,         // Ctrl+T
+    OpenFilePicker,       // Ctrl+O
+    PickerQueryChanged(String),
+    PickerMove(isize),
+    PickerSelect(usize),  // Index into the picker's matches
+    ClosePicker,
+    ExcerptStartChanged(String),
+    ExcerptEndChanged(String),
+    InsertExcerpt,
+    CloseExcerpt,
+    NextPlaceholder,      // Tab while a snippet's placeholders are pending
+    ExpandAbbreviation,   // Tab right after an abbreviation
+    UndoExpansion,        // Ctrl+Z right after an expansion
+    ToggleGitPanel,       // Ctrl+G
+    InsertGitContext(GitContext),
+    ToggleHunk(usize, bool),
+    InsertHunks,
+    PasteClipboard,       // Ctrl+V: HTML as Markdown, text as usual, an image is saved and its path inserted
+    PastePlainText,       // Ctrl+Shift+V
+    SelectIssue(Issue),   // Ignored unless still in the current issues (the text may have changed)
+    FixIssue(Issue),
+    SuggestSpelling,      // Ctrl+.: spelling suggestions for the word at the cursor
+    ConvertPunctuation,   // Ctrl+P: apply the punctuation profile to the selection (or everything)
+    TogglePasteTransform(PasteTransform, bool),
+    AcceptPaste,
+    PasteOriginal,
+    ClosePastePreview,
+    ImagePasted(Result<Option<PathBuf>, String>),
+    MoveWord { forward: bool, select: bool }, // Ctrl+←/→ (with Shift to select)
+    DeleteWordBackward,   // Ctrl+Backspace
+    ToggleRomaji,         // Ctrl+J: off -> hiragana -> katakana
+    RomajiInput(char),
+    RomajiConvert,        // Space while composing: next kanji candidate
+    RomajiBackspace,
+    RomajiCommit,         // Enter while composing
+    RomajiCancel,         // Esc while composing
+    ToggleForwarding,
+    ForwardKey(ForwardKey), // Send a single key to the terminal, then come back
+    KeyForwarded(Result<(), String>),
+    FlashTick,
+    Ipc(ipc::Incoming),
     Event(Event),
 }
 
@@ -103,38 +382,1343 @@ fn get_config() -> Option<&'static ResidentConfigData> {
     RESIDENT_CONFIG.get()
 }
 
+/// Editor text with line endings normalized (\r\n -> \n) and trailing whitespace trimmed
+fn editor_text(state: &ResidentClaudeInput) -> String {
+    state.content.text().replace("\r\n", "\n").trim_end().to_string()
+}
+
+/// Editor text as it is sent: `editor_text` plus the configured Japanese normalization
+/// (and the punctuation profile when it applies on send)
+fn prompt_text(state: &ResidentClaudeInput) -> String {
+    let text = normalize::normalize(&editor_text(state), &state.config.normalization);
+    let profile = &state.config.punctuation;
+    if profile.on_send && profile.is_active() {
+        punctuation::convert(&text, profile)
+    } else {
+        text
+    }
+}
+
+/// The prompt to send, including romaji still being composed
+/// The composed text is inserted directly: completion and abbreviations don't matter for text
+/// that is about to be sent
+fn take_prompt(state: &mut ResidentClaudeInput) -> String {
+    let preedit = std::mem::take(&mut state.preedit);
+    let composed = preedit.text(state.romaji_mode);
+    if !composed.is_empty() {
+        state.content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(composed))));
+    }
+    prompt_text(state)
+}
+
+/// Working directory of the Claude session (falls back to our own)
+fn session_cwd() -> Option<String> {
+    get_config()
+        .and_then(|c| c.cwd.clone())
+        .or_else(|| std::env::current_dir().ok().map(|d| d.to_string_lossy().into_owned()))
+}
+
+/// Text of the cursor line up to the cursor (None while a selection is active)
+fn text_before_cursor(content: &text_editor::Content) -> Option<String> {
+    let cursor = content.cursor();
+    if cursor.selection.is_some() {
+        return None;
+    }
+    let line = content.line(cursor.position.line)?;
+    line.text.get(..cursor.position.column).map(str::to_string)
+}
+
+/// Byte offset of an editor position in the editor's text
+fn text_offset(text: &str, position: text_editor::Position) -> usize {
+    let line_start: usize = text.split('\n').take(position.line).map(|line| line.len() + 1).sum();
+    (line_start + position.column).min(text.len())
+}
+
+/// Replace the `len` bytes before the cursor with `replacement` (a single undo step)
+fn replace_before_cursor(content: &mut text_editor::Content, len: usize, replacement: &str) {
+    let position = content.cursor().position;
+    if len > 0 {
+        content.move_to(text_editor::Cursor {
+            position: text_editor::Position {
+                line: position.line,
+                column: position.column.saturating_sub(len),
+            },
+            selection: Some(position),
+        });
+    }
+    content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(
+        replacement.to_string(),
+    ))));
+}
+
+/// Slash command candidates while the first line is a bare `/command` prefix
+fn slash_completion(state: &mut ResidentClaudeInput) -> Option<Completion> {
+    if state.content.cursor().position.line != 0 {
+        return None;
+    }
+    let prefix = text_before_cursor(&state.content)?;
+    let query = prefix.strip_prefix('/')?;
+    if query.contains(char::is_whitespace) {
+        return None;
+    }
+    // Pick up commands added since the window opened
+    if query.is_empty() {
+        state.commands = commands::load_commands(session_cwd().as_deref());
+    }
+
+    let mut matches: Vec<(i64, &SlashCommand)> = state
+        .commands
+        .iter()
+        .filter_map(|c| Some((completion::fuzzy_score(query, &c.name[1..])?, c)))
+        .collect();
+    // Stable sort keeps custom commands ahead of built-ins on equal score
+    matches.sort_by_key(|(score, _)| -score);
+
+    let items = matches
+        .into_iter()
+        .take(completion::MAX_ITEMS)
+        .map(|(_, c)| CompletionItem {
+            label: c.name.clone(),
+            detail: c.description.clone(),
+            insert: if c.argument_hint.is_some() {
+                format!("{} ", c.name)
+            } else {
+                c.name.clone()
+            },
+        })
+        .collect();
+    Completion::new(items, prefix.len())
+}
+
+/// File candidates for the `@mention` before the cursor
+fn file_completion(state: &ResidentClaudeInput) -> Option<Completion> {
+    let prefix = text_before_cursor(&state.content)?;
+    let query = files::mention_query(&prefix)?;
+    let items = file_items(&state.files, query, |path| format!("@{} ", path));
+    Completion::new(items, query.len() + 1)
+}
+
+/// Project files fuzzy-matching `query`, best first (file name as label, directory as detail)
+fn file_items(files: &[String], query: &str, insert: impl Fn(&str) -> String) -> Vec<CompletionItem> {
+    let mut matches: Vec<(i64, &String)> = files
+        .iter()
+        .filter_map(|path| Some((completion::fuzzy_score(query, path)?, path)))
+        .collect();
+    matches.sort_by_key(|(score, _)| -score);
+
+    matches
+        .into_iter()
+        .take(completion::MAX_ITEMS)
+        .map(|(_, path)| {
+            let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+            CompletionItem {
+                label: name.to_string(),
+                detail: dir.to_string(),
+                insert: insert(path),
+            }
+        })
+        .collect()
+}
+
+/// Project identifiers completing the ASCII word before the cursor (Tab to accept)
+fn identifier_completion(state: &ResidentClaudeInput) -> Option<Completion> {
+    let prefix = text_before_cursor(&state.content)?;
+    let query = identifiers::identifier_query(&prefix)?;
+    let counts = state.identifiers.read().ok()?;
+
+    let items = identifiers::complete(&counts, query, completion::MAX_ITEMS)
+        .into_iter()
+        .map(|(word, count)| CompletionItem {
+            label: word.to_string(),
+            detail: format!("×{}", count),
+            insert: word.to_string(),
+        })
+        .collect();
+    Completion::new(items, query.len()).map(Completion::tab_only)
+}
+
+/// List the session's project files on a worker thread
+fn load_files() -> Task<ResidentMessage> {
+    let Some(cwd) = session_cwd() else {
+        return Task::none();
+    };
+    let (sender, receiver) = iced::futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(files::list_files(std::path::Path::new(&cwd)));
+    });
+    Task::perform(receiver, |files| ResidentMessage::FilesLoaded(Arc::new(files.unwrap_or_default())))
+}
+
+/// Entries matching the picker's query
+fn picker_matches(kind: &PickerKind, files: &[String], query: &str) -> Option<Completion> {
+    let items = match kind {
+        PickerKind::Snippets(snippets) => {
+            let mut matches: Vec<(i64, &Snippet)> = snippets
+                .iter()
+                .filter_map(|s| Some((completion::fuzzy_score(query, &s.name)?, s)))
+                .collect();
+            matches.sort_by_key(|(score, _)| -score);
+            matches
+                .into_iter()
+                .take(completion::MAX_ITEMS)
+                .map(|(_, s)| CompletionItem {
+                    label: s.name.clone(),
+                    detail: s.description.clone(),
+                    insert: s.name.clone(),
+                })
+                .collect()
+        }
+        PickerKind::Files => file_items(files, query, str::to_string),
+    };
+    Completion::new(items, 0)
+}
+
+/// Read a file for an excerpt on a worker thread (it may be up to 16 MB)
+fn load_excerpt(path: PathBuf) -> Task<ResidentMessage> {
+    let (sender, receiver) = iced::futures::channel::oneshot::channel();
+    let reading = path.clone();
+    std::thread::spawn(move || {
+        let _ = sender.send(files::read_file_content(&reading).map(Arc::new));
+    });
+    Task::perform(receiver, move |content| {
+        let content = content.unwrap_or_else(|_| Err("File read was interrupted".to_string()));
+        ResidentMessage::ExcerptLoaded(path.clone(), content)
+    })
+}
+
+/// Show the line range panel for a file that has been read
+fn open_excerpt(state: &mut ResidentClaudeInput, path: PathBuf, content: Arc<files::FileContent>) {
+    let display = session_cwd()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(|p| p.to_string_lossy().replace('\\', "/")))
+        .unwrap_or_else(|| path.display().to_string());
+
+    // Preselect the whole file, or just its start when it's large
+    let end = if content.size > files::LARGE_FILE_BYTES {
+        content.line_count.min(files::LARGE_FILE_DEFAULT_LINES)
+    } else {
+        content.line_count
+    };
+    state.excerpt = Some(Excerpt {
+        path,
+        display,
+        start: "1".to_string(),
+        end: end.to_string(),
+        content,
+    });
+}
+
+/// Insert the chosen lines as a fenced block headed by `path:start-end`
+fn insert_excerpt(state: &mut ResidentClaudeInput) -> Result<(), String> {
+    let Some(excerpt) = state.excerpt.take() else {
+        return Ok(());
+    };
+    // Enter in the line inputs gets here even while the Insert button is disabled
+    if excerpt.content.binary {
+        state.excerpt = Some(excerpt);
+        return Err("Binary file: it can't be inserted as text".to_string());
+    }
+    let parse = |value: &str| value.trim().parse::<usize>().ok().filter(|&n| n >= 1);
+    let range = match (parse(&excerpt.start), parse(&excerpt.end)) {
+        (Some(start), Some(end)) if start <= end && start <= excerpt.content.line_count => Some((start, end)),
+        _ => None,
+    };
+    let Some((start, end)) = range else {
+        let message = format!(
+            "Invalid line range: {}-{} (the file has {} lines)",
+            excerpt.start, excerpt.end, excerpt.content.line_count
+        );
+        state.excerpt = Some(excerpt);
+        return Err(message);
+    };
+    let end = end.min(excerpt.content.line_count);
+    let lines = files::line_range(&excerpt.content.text, start, end);
+    let language = files::language_for_path(&excerpt.path);
+    let block = format!("{}:{}-{}\n{}", excerpt.display, start, end, files::fenced_block(language, &lines));
+    insert_block(&mut state.content, &block);
+    Ok(())
+}
+
+/// Editor position of a byte offset in text inserted at `base`
+fn position_in_inserted(text: &str, offset: usize, base: text_editor::Position) -> text_editor::Position {
+    let before = &text[..offset];
+    match before.rfind('\n') {
+        Some(newline) => text_editor::Position {
+            line: base.line + before.matches('\n').count(),
+            column: offset - newline - 1,
+        },
+        None => text_editor::Position {
+            line: base.line,
+            column: base.column + offset,
+        },
+    }
+}
+
+/// Select a placeholder so typing replaces its default text (an empty range just moves the cursor)
+fn select_range(content: &mut text_editor::Content, start: text_editor::Position, end: text_editor::Position) {
+    // Moving to a cursor without a selection keeps an existing one, so collapse it first
+    if content.cursor().selection.is_some() {
+        content.perform(text_editor::Action::Move(text_editor::Motion::Left));
+    }
+    content.move_to(text_editor::Cursor {
+        position: end,
+        selection: (start != end).then_some(start),
+    });
+}
+
+/// Insert a snippet at the cursor and select its first placeholder
+fn insert_snippet(state: &mut ResidentClaudeInput, snippet: &Snippet) {
+    let (text, tabstops) = snippets::expand(snippet, session_cwd().as_deref());
+
+    // Pasting replaces the selection, so the text starts where the selection does
+    let cursor = state.content.cursor();
+    let base = match cursor.selection {
+        Some(selection) if (selection.line, selection.column) < (cursor.position.line, cursor.position.column) => selection,
+        _ => cursor.position,
+    };
+    state
+        .content
+        .perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text.clone()))));
+
+    let mut stops = tabstops.iter().map(|t| {
+        (
+            position_in_inserted(&text, t.start, base),
+            position_in_inserted(&text, t.end, base),
+        )
+    });
+    state.snippet_session = stops.next().map(|(start, end)| {
+        select_range(&mut state.content, start, end);
+        SnippetSession {
+            current: (start, end),
+            remaining: stops.collect(),
+        }
+    });
+}
+
+/// Move to the next placeholder (assumes edits stay inside the current placeholder)
+fn next_placeholder(state: &mut ResidentClaudeInput) {
+    let Some(mut session) = state.snippet_session.take() else {
+        return;
+    };
+    if session.remaining.is_empty() {
+        return;
+    }
+    // Placeholders after the edited one move by what was typed; earlier ones stay put
+    let cursor = state.content.cursor().position;
+    let (old_start, old_end) = session.current;
+    let shift = |p: text_editor::Position| {
+        if (p.line, p.column) <= (old_start.line, old_start.column) {
+            p
+        } else if p.line == old_end.line {
+            text_editor::Position {
+                line: cursor.line,
+                column: cursor.column + p.column.saturating_sub(old_end.column),
+            }
+        } else {
+            text_editor::Position {
+                line: (p.line + cursor.line).saturating_sub(old_end.line),
+                column: p.column,
+            }
+        }
+    };
+
+    let (start, end) = session.remaining.remove(0);
+    let (start, end) = (shift(start), shift(end));
+    session.remaining = session.remaining.into_iter().map(|(s, e)| (shift(s), shift(e))).collect();
+    select_range(&mut state.content, start, end);
+    session.current = (start, end);
+    if !session.remaining.is_empty() {
+        state.snippet_session = Some(session);
+    }
+}
+
+/// Insert a block of text at the cursor, starting on a new line
+fn insert_block(content: &mut text_editor::Content, block: &str) {
+    let mid_line = text_before_cursor(content).is_some_and(|p| !p.is_empty());
+    let text = if mid_line { format!("\n{}", block) } else { block.to_string() };
+    content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))));
+}
+
+/// Insert git output as a fenced block (capped at git::MAX_CONTEXT_BYTES)
+fn insert_git_output(state: &mut ResidentClaudeInput, output: &str, language: &str) {
+    let (output, truncated) = git::truncate(output, git::MAX_CONTEXT_BYTES);
+    insert_block(&mut state.content, &files::fenced_block(language, &output));
+    state.status_message = truncated.then(|| format!("Truncated to {} KB", git::MAX_CONTEXT_BYTES / 1024));
+}
+
+/// Run the chosen git command in the session cwd and insert its output
+fn insert_git_context(state: &mut ResidentClaudeInput, context: GitContext) -> Result<(), String> {
+    let cwd = session_cwd().ok_or("No working directory for this session")?;
+    let cwd = std::path::Path::new(&cwd);
+    match context {
+        GitContext::Branch => {
+            let branch = git::current_branch(cwd).ok_or("Not on a git branch")?;
+            state.content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(
+                format!("`{}`", branch),
+            ))));
+        }
+        GitContext::Status => {
+            let status = git::status_short(cwd)?;
+            if status.trim().is_empty() {
+                return Err("Working tree clean (nothing to insert)".to_string());
+            }
+            insert_git_output(state, &status, "text");
+        }
+        GitContext::StagedDiff => {
+            let diff = git::staged_diff(cwd)?;
+            if diff.trim().is_empty() {
+                return Err("Nothing staged (nothing to insert)".to_string());
+            }
+            insert_git_output(state, &diff, "diff");
+        }
+        GitContext::Hunks => {
+            let hunks = git::diff_hunks(cwd)?;
+            if hunks.is_empty() {
+                return Err("No uncommitted changes (nothing to insert)".to_string());
+            }
+            // Keep the panel open with the hunk list
+            state.git_panel = Some(GitPanel {
+                hunks: Some(hunks.into_iter().map(|h| (h, false)).collect()),
+            });
+            return Ok(());
+        }
+    }
+    state.git_panel = None;
+    Ok(())
+}
+
+/// Expand the abbreviation before the cursor, followed by `trigger`; false if there is none
+/// The abbreviation is replaced in a single edit, so Ctrl+Z can put it back in one step
+fn expand_abbreviation(state: &mut ResidentClaudeInput, trigger: &str) -> bool {
+    let Some(prefix) = text_before_cursor(&state.content) else {
+        return false;
+    };
+    let Some((abbreviation, expansion)) = abbreviations::find(&prefix, &state.config.abbreviations) else {
+        return false;
+    };
+    let inserted = format!("{}{}", expansion, trigger);
+    replace_before_cursor(&mut state.content, abbreviation.len(), &inserted);
+    state.last_expansion = Some(Expansion {
+        typed: format!("{}{}", abbreviation, trigger),
+        inserted,
+    });
+    true
+}
+
+/// Put back the abbreviation that was just expanded
+fn undo_expansion(state: &mut ResidentClaudeInput) {
+    let Some(expansion) = state.last_expansion.take() else {
+        return;
+    };
+    if text_before_cursor(&state.content).is_some_and(|p| p.ends_with(&expansion.inserted)) {
+        replace_before_cursor(&mut state.content, expansion.inserted.len(), &expansion.typed);
+    }
+}
+
+/// Recompute the popup after the text changed
+fn refresh_completion(state: &mut ResidentClaudeInput) -> Task<ResidentMessage> {
+    state.completion = slash_completion(state)
+        .or_else(|| file_completion(state))
+        .or_else(|| identifier_completion(state));
+
+    // A freshly typed `@` relists the files so new ones show up
+    let new_mention = text_before_cursor(&state.content)
+        .is_some_and(|prefix| files::mention_query(&prefix) == Some(""));
+    if new_mention {
+        load_files()
+    } else {
+        Task::none()
+    }
+}
+
+/// Argument hint for the slash command being typed (e.g. `/compact [instructions]`)
+fn argument_hint(state: &ResidentClaudeInput) -> Option<String> {
+    let first_line = state.content.line(0)?;
+    let (name, _) = first_line.text.split_once(char::is_whitespace)?;
+    let command = state.commands.iter().find(|c| c.name == name)?;
+    Some(format!("{} {}", command.name, command.argument_hint.as_ref()?))
+}
+
+/// Proofreading and spelling issues in `text` (empty when disabled)
+fn lint_issues(state: &ResidentClaudeInput, text: &str) -> Vec<Issue> {
+    let mut issues = if state.config.lint.enabled {
+        lint::lint(text, &state.config.lint)
+    } else {
+        Vec::new()
+    };
+    if let Some(dictionary) = state.dictionary.get().filter(|_| state.config.spellcheck.enabled) {
+        issues.extend(spell::misspellings(text, dictionary, &state.project_words.1));
+        issues.sort_by_key(|issue| (issue.line, issue.range.start));
+    }
+    issues
+}
+
+/// Recompute the cached issues when the text changed or the dictionary has finished loading
+fn refresh_issues(state: &mut ResidentClaudeInput) {
+    let source = (state.content.text(), state.dictionary.get().is_some());
+    if source != state.issues_source {
+        state.issues = lint_issues(state, &source.0);
+        state.issues_source = source;
+    }
+}
+
+/// Handle a message, then bring the proofreading issues up to date for the next view
+fn resident_update_and_proofread(state: &mut ResidentClaudeInput, message: ResidentMessage) -> Task<ResidentMessage> {
+    let task = resident_update(state, message);
+    refresh_issues(state);
+    task
+}
+
+/// Re-harvest the spellchecker's project words when the identifier index has changed
+fn refresh_project_words(state: &mut ResidentClaudeInput) {
+    let Ok(counts) = state.identifiers.read() else {
+        return;
+    };
+    if counts.len() != state.project_words.0 {
+        state.project_words = (counts.len(), spell::harvest_words(counts.keys()));
+    }
+}
+
+/// Offer the dictionary's suggestions for a misspelled word in the completion popup
+fn suggest_spelling(state: &mut ResidentClaudeInput, issue: &Issue) {
+    let Some(dictionary) = state.dictionary.get() else {
+        return;
+    };
+    let Some(line) = state.content.line(issue.line) else {
+        return;
+    };
+    let word = line.text[issue.range.clone()].to_string();
+    let items = dictionary
+        .suggest(&word)
+        .into_iter()
+        .map(|suggestion| CompletionItem {
+            label: suggestion.clone(),
+            detail: String::new(),
+            insert: suggestion,
+        })
+        .collect();
+
+    // The popup replaces the text before the cursor, so put the cursor after the word
+    let end = text_editor::Position {
+        line: issue.line,
+        column: issue.range.end,
+    };
+    select_range(&mut state.content, end, end);
+    state.completion = Completion::new(items, issue.range.len());
+    if state.completion.is_none() {
+        state.status_message = Some(format!("No suggestions for \"{}\"", word));
+    }
+}
+
+/// Select an issue's range in the editor
+fn select_issue(content: &mut text_editor::Content, issue: &Issue) {
+    let position = |column| text_editor::Position { line: issue.line, column };
+    select_range(content, position(issue.range.start), position(issue.range.end));
+}
+
+/// Insert the composed text (kana or the chosen candidate) at the cursor
+fn commit_preedit(state: &mut ResidentClaudeInput) -> Task<ResidentMessage> {
+    let preedit = std::mem::take(&mut state.preedit);
+    let text = preedit.text(state.romaji_mode);
+    if text.is_empty() {
+        return Task::none();
+    }
+    paste_text(state, text)
+}
+
+/// Paste text as if typed (so abbreviations and completion react to it)
+fn paste_text(state: &mut ResidentClaudeInput, text: String) -> Task<ResidentMessage> {
+    let text = text.replace("\r\n", "\n");
+    let paste = text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text)));
+    resident_update(state, ResidentMessage::EditorAction(paste))
+}
+
+/// Paste multi-line text through the configured cleanups (previewed first unless disabled)
+fn paste_cleaned(state: &mut ResidentClaudeInput, text: String, markdown: bool) -> Task<ResidentMessage> {
+    let text = text.replace("\r\n", "\n");
+    let enabled = state.config.paste_transforms.clone();
+    if !text.trim_end().contains('\n') || enabled.is_empty() {
+        return paste_text(state, text);
+    }
+    let cleaned = clean_paste(&text, &enabled, markdown);
+    if cleaned.trim_end_matches('\n') == text.trim_end_matches('\n') {
+        return paste_text(state, text);
+    }
+    if !state.config.paste_preview {
+        return paste_text(state, cleaned);
+    }
+    state.paste_preview = Some(PastePreview {
+        original: text,
+        enabled,
+        cleaned,
+        markdown,
+    });
+    Task::none()
+}
+
+/// Send text to the bound terminal: write to clipboard, then paste directly
+fn deliver_to_terminal(text: &str) -> Result<(), String> {
+    clipboard_utils::write_to_clipboard(text).map_err(|e| format!("Clipboard error: {}", e))?;
+
+    let hwnd = get_config().and_then(|c| c.terminal_hwnd);
+    logger::log(&format!("[DEBUG app] terminal_hwnd from config: {:?}", hwnd));
+    terminal::paste_to_terminal(hwnd).map_err(|e| format!("Send error: {}", e))
+}
+
+/// Produce a message after a delay without blocking the UI thread
+fn after(delay: Duration, message: ResidentMessage) -> Task<ResidentMessage> {
+    let (sender, receiver) = iced::futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        let _ = sender.send(());
+    });
+    Task::perform(receiver, move |_| message.clone())
+}
+
+/// Rewrite the registry entry after the instance info changed
+fn save_instance(state: &ResidentClaudeInput) {
+    if let Some(instance) = &state.instance {
+        if let Err(e) = ipc::register_instance(instance) {
+            logger::log(&format!("[DEBUG app] {}", e));
+        }
+    }
+}
+
+fn handle_ipc(state: &mut ResidentClaudeInput, incoming: ipc::Incoming) -> Task<ResidentMessage> {
+    let ipc::Incoming { request, reply } = incoming;
+    match request {
+        ipc::Request::RegisterSession { session_id, transcript_path } => {
+            if !transcript_path.is_empty() {
+                state.transcript_path = Some(transcript_path);
+            }
+            if let Some(instance) = &mut state.instance {
+                if !instance.sessions.contains(&session_id) {
+                    instance.sessions.push(session_id);
+                }
+            }
+            save_instance(state);
+            reply.send(ipc::Response::ok("Session registered"));
+            Task::none()
+        }
+        ipc::Request::Stop { session_id, transcript_path } => {
+            let owns_session = state
+                .instance
+                .as_ref()
+                .is_some_and(|i| i.sessions.contains(&session_id));
+            if !owns_session {
+                reply.send(ipc::Response::error("Unknown session"));
+                return Task::none();
+            }
+            state.stop_count += 1;
+            if !transcript_path.is_empty() {
+                state.transcript_path = Some(transcript_path);
+            }
+            if state.queue.is_empty() {
+                reply.send(ipc::Response::ok("Queue is empty"));
+                return Task::none();
+            }
+            // Reply first so the hook returns before we paste into the terminal
+            reply.send(ipc::Response::ok("Sending next queued prompt"));
+            after(QUEUE_SEND_DELAY, ResidentMessage::SendQueued)
+        }
+        ipc::Request::Notify { session_id, message } => {
+            let owns_session = state
+                .instance
+                .as_ref()
+                .is_some_and(|i| i.sessions.contains(&session_id));
+            if !owns_session {
+                reply.send(ipc::Response::error("Unknown session"));
+                return Task::none();
+            }
+            reply.send(ipc::Response::ok("Notification shown"));
+            state.notification = Some(message);
+
+            // Start flashing (restart the count if already flashing)
+            let already_flashing = state.flash_remaining > 0;
+            state.flash_remaining = FLASH_TOGGLES;
+            state.flash_on = true;
+            let flash = if already_flashing {
+                Task::none()
+            } else {
+                after(FLASH_INTERVAL, ResidentMessage::FlashTick)
+            };
+
+            let attention = if state.config.request_attention {
+                window::latest().and_then(|id| {
+                    window::request_user_attention(id, Some(window::UserAttention::Informational))
+                })
+            } else {
+                Task::none()
+            };
+            Task::batch([flash, attention])
+        }
+        ipc::Request::StatusLine { session_id, info } => {
+            let owns_session = state
+                .instance
+                .as_ref()
+                .is_some_and(|i| i.sessions.contains(&session_id));
+            if owns_session {
+                state.status_line = Some(info);
+                reply.send(ipc::Response::ok("Status updated"));
+            } else {
+                reply.send(ipc::Response::error("Unknown session"));
+            }
+            Task::none()
+        }
+        ipc::Request::AskUser { question } => {
+            if state.pending_question.is_some() {
+                reply.send(ipc::Response::error("Another question is already waiting for an answer"));
+                return Task::none();
+            }
+            state.pending_question = Some((question, reply));
+
+            // Bring the window forward so the question is noticed
+            let gain_focus = window::latest().and_then(window::gain_focus);
+            let attention = window::latest().and_then(|id| {
+                window::request_user_attention(id, Some(window::UserAttention::Critical))
+            });
+            Task::batch([gain_focus, attention, focus(EDITOR_ID.clone())])
+        }
+        ipc::Request::Deliver { text, deferred: true } => {
+            reply.send(ipc::Response::ok("Scheduled"));
+            after(QUEUE_SEND_DELAY, ResidentMessage::DeliverDeferred(text))
+        }
+        ipc::Request::Deliver { text, deferred: false } => {
+            match deliver_to_terminal(&text) {
+                Ok(()) => {
+                    reply.send(ipc::Response::ok("Delivered"));
+                }
+                Err(e) => {
+                    state.status_message = Some(e.clone());
+                    reply.send(ipc::Response::error(e));
+                }
+            }
+            Task::none()
+        }
+        ipc::Request::AskClosed => {
+            // Claude cancelled the question (or its MCP server exited)
+            if state.pending_question.as_ref().is_some_and(|(_, reply)| reply.is_closed()) {
+                state.pending_question = None;
+                state.status_message = Some("Claude withdrew its question".to_string());
+            }
+            Task::none()
+        }
+        ipc::Request::Status => {
+            reply.send(ipc::Response {
+                ok: true,
+                status: Some(ipc::InstanceStatus {
+                    stop_count: state.stop_count,
+                    transcript_path: state.transcript_path.clone(),
+                }),
+                ..Default::default()
+            });
+            Task::none()
+        }
+    }
+}
+
 fn resident_update(state: &mut ResidentClaudeInput, message: ResidentMessage) -> Task<ResidentMessage> {
     match message {
         ResidentMessage::EditorAction(action) => {
+            state.last_expansion = None;
+
+            // A space after an abbreviation expands it (IME commits arrive as paste)
+            let trigger = match &action {
+                text_editor::Action::Edit(text_editor::Edit::Insert(c)) => Some(c.to_string()),
+                text_editor::Action::Edit(text_editor::Edit::Paste(text)) => Some(text.to_string()),
+                _ => None,
+            };
+            if let Some(trigger) = trigger.filter(|t| abbreviations::TRIGGERS.contains(&t.as_str())) {
+                if expand_abbreviation(state, &trigger) {
+                    state.completion = None;
+                    return Task::none();
+                }
+            }
+
+            // Double-click on Japanese text selects a word rather than the whole run
+            if matches!(action, text_editor::Action::SelectWord) {
+                let position = state.content.cursor().position;
+                let word = state
+                    .content
+                    .line(position.line)
+                    .and_then(|line| words::word_at(&line.text, position.column));
+                if let Some(word) = word {
+                    let at = |column| text_editor::Position { line: position.line, column };
+                    select_range(&mut state.content, at(word.start), at(word.end));
+                    state.completion = None;
+                    return Task::none();
+                }
+            }
+
+            let is_edit = action.is_edit();
+            let is_scroll = matches!(action, text_editor::Action::Scroll { .. });
             state.content.perform(action);
+            if is_edit {
+                refresh_project_words(state);
+                return refresh_completion(state);
+            }
+            if !is_scroll {
+                state.completion = None;
+                state.snippet_session = None;
+            }
+            Task::none()
+        }
+        ResidentMessage::CompletionMove(delta) => {
+            if let Some(completion) = &mut state.completion {
+                completion.move_selection(delta);
+            }
+            Task::none()
+        }
+        ResidentMessage::AcceptCompletion(index) => {
+            if let Some(completion) = state.completion.take() {
+                if let Some(item) = completion.items.get(index) {
+                    replace_before_cursor(&mut state.content, completion.replace_len, &item.insert);
+                }
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::CloseCompletion => {
+            state.completion = None;
+            Task::none()
+        }
+        ResidentMessage::OpenSnippets => {
+            let snippets = snippets::load_snippets(session_cwd().as_deref());
+            if snippets.is_empty() {
+                let dirs = snippets::snippet_dirs(session_cwd().as_deref());
+                state.status_message = Some(format!("No snippets in {}", dirs[0].display()));
+                return Task::none();
+            }
+            let kind = PickerKind::Snippets(snippets);
+            state.completion = None;
+            state.picker = Some(Picker {
+                matches: picker_matches(&kind, &state.files, ""),
+                kind,
+                query: String::new(),
+            });
+            focus(PICKER_SEARCH_ID.clone())
+        }
+        ResidentMessage::OpenFilePicker => {
+            state.completion = None;
+            state.picker = Some(Picker {
+                matches: picker_matches(&PickerKind::Files, &state.files, ""),
+                kind: PickerKind::Files,
+                query: String::new(),
+            });
+            // Relist in the background; the picker updates when the list arrives
+            Task::batch([focus(PICKER_SEARCH_ID.clone()), load_files()])
+        }
+        ResidentMessage::PickerQueryChanged(query) => {
+            if let Some(picker) = &mut state.picker {
+                picker.matches = picker_matches(&picker.kind, &state.files, &query);
+                picker.query = query;
+            }
+            Task::none()
+        }
+        ResidentMessage::PickerMove(delta) => {
+            if let Some(matches) = state.picker.as_mut().and_then(|p| p.matches.as_mut()) {
+                matches.move_selection(delta);
+            }
+            Task::none()
+        }
+        ResidentMessage::PickerSelect(index) => {
+            let Some(picker) = state.picker.take() else {
+                return Task::none();
+            };
+            let Some(item) = picker.matches.as_ref().and_then(|m| m.items.get(index)) else {
+                return focus(EDITOR_ID.clone());
+            };
+            match &picker.kind {
+                PickerKind::Snippets(snippets) => {
+                    if let Some(snippet) = snippets.iter().find(|s| s.name == item.insert) {
+                        insert_snippet(state, snippet);
+                    }
+                }
+                PickerKind::Files => {
+                    if let Some(cwd) = session_cwd() {
+                        return Task::batch([load_excerpt(Path::new(&cwd).join(&item.insert)), focus(EDITOR_ID.clone())]);
+                    }
+                }
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::ClosePicker => {
+            state.picker = None;
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::ExcerptStartChanged(value) => {
+            if let Some(excerpt) = &mut state.excerpt {
+                excerpt.start = value;
+            }
+            Task::none()
+        }
+        ResidentMessage::ExcerptEndChanged(value) => {
+            if let Some(excerpt) = &mut state.excerpt {
+                excerpt.end = value;
+            }
+            Task::none()
+        }
+        ResidentMessage::InsertExcerpt => {
+            state.status_message = None;
+            if let Err(e) = insert_excerpt(state) {
+                state.status_message = Some(e);
+                return Task::none();
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::CloseExcerpt => {
+            state.excerpt = None;
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::NextPlaceholder => {
+            next_placeholder(state);
+            Task::none()
+        }
+        ResidentMessage::ExpandAbbreviation => {
+            expand_abbreviation(state, "");
+            state.completion = None;
+            Task::none()
+        }
+        ResidentMessage::UndoExpansion => {
+            undo_expansion(state);
+            Task::none()
+        }
+        ResidentMessage::ToggleGitPanel => {
+            state.git_panel = match state.git_panel {
+                Some(_) => None,
+                None => Some(GitPanel { hunks: None }),
+            };
+            Task::none()
+        }
+        ResidentMessage::InsertGitContext(context) => {
+            state.status_message = None;
+            if let Err(e) = insert_git_context(state, context) {
+                state.status_message = Some(e);
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::ToggleHunk(index, chosen) => {
+            if let Some(hunk) = state
+                .git_panel
+                .as_mut()
+                .and_then(|p| p.hunks.as_mut())
+                .and_then(|h| h.get_mut(index))
+            {
+                hunk.1 = chosen;
+            }
+            Task::none()
+        }
+        ResidentMessage::InsertHunks => {
+            if let Some(hunks) = state.git_panel.take().and_then(|p| p.hunks) {
+                let chosen: Vec<&git::Hunk> = hunks.iter().filter(|(_, c)| *c).map(|(h, _)| h).collect();
+                if !chosen.is_empty() {
+                    insert_git_output(state, &git::hunks_to_diff(&chosen), "diff");
+                }
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::PasteClipboard => {
+            // Rich text from browsers and documents keeps its structure as Markdown
+            let markdown = clipboard_utils::read_html_from_clipboard().and_then(|html| markdown::html_to_markdown(&html));
+            if let Some(markdown) = markdown {
+                logger::log(&format!("[DEBUG app] Pasting HTML as Markdown ({} chars)", markdown.len()));
+                return paste_cleaned(state, markdown, true);
+            }
+            if let Ok(text) = clipboard_utils::read_from_clipboard() {
+                if !text.is_empty() {
+                    return paste_cleaned(state, text, false);
+                }
+            }
+            // No text: save a screenshot on a worker thread (PNG encoding takes a moment)
+            let session = state.instance.as_ref().and_then(|i| i.sessions.last());
+            let dir = paths::attachments_dir(session.map(String::as_str));
+            let (sender, receiver) = iced::futures::channel::oneshot::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(clipboard_utils::save_clipboard_image(&dir));
+            });
+            Task::perform(receiver, |result| {
+                ResidentMessage::ImagePasted(result.unwrap_or_else(|_| Err("Image paste was cancelled".to_string())))
+            })
+        }
+        ResidentMessage::PastePlainText => match clipboard_utils::read_from_clipboard() {
+            Ok(text) => paste_text(state, text),
+            Err(e) => {
+                state.status_message = Some(e);
+                Task::none()
+            }
+        },
+        ResidentMessage::SelectIssue(issue) => {
+            if !state.issues.contains(&issue) {
+                return focus(EDITOR_ID.clone());
+            }
+            if issue.rule == Rule::Spelling {
+                suggest_spelling(state, &issue);
+            } else {
+                select_issue(&mut state.content, &issue);
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::FixIssue(issue) => {
+            if !state.issues.contains(&issue) {
+                return Task::none();
+            }
+            if let Some(fix) = issue.fix.clone() {
+                select_issue(&mut state.content, &issue);
+                let edit = if fix.is_empty() {
+                    text_editor::Edit::Delete
+                } else {
+                    text_editor::Edit::Paste(Arc::new(fix))
+                };
+                state.content.perform(text_editor::Action::Edit(edit));
+            }
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::SuggestSpelling => {
+            let cursor = state.content.cursor().position;
+            let issue = state
+                .issues
+                .iter()
+                .find(|issue| {
+                    issue.rule == Rule::Spelling
+                        && issue.line == cursor.line
+                        && (issue.range.start..=issue.range.end).contains(&cursor.column)
+                })
+                .cloned();
+            match issue {
+                Some(issue) => suggest_spelling(state, &issue),
+                None => state.status_message = Some("No misspelled word at the cursor".to_string()),
+            }
+            Task::none()
+        }
+        ResidentMessage::ConvertPunctuation => {
+            if !state.config.punctuation.is_active() {
+                state.status_message = Some("No punctuation profile configured".to_string());
+                return Task::none();
+            }
+            let cursor = state.content.cursor();
+            let text = state.content.text();
+            let converted = match cursor.selection {
+                Some(anchor) => {
+                    let (start, end) = (text_offset(&text, anchor), text_offset(&text, cursor.position));
+                    let selection = start.min(end)..start.max(end);
+                    punctuation::convert_selection(&text, selection, &state.config.punctuation)
+                }
+                None => {
+                    state.content.perform(text_editor::Action::SelectAll);
+                    punctuation::convert(&state.content.selection().unwrap_or_default(), &state.config.punctuation)
+                }
+            };
+            state.content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(converted))));
+            Task::none()
+        }
+        ResidentMessage::TogglePasteTransform(transform, enabled) => {
+            if let Some(preview) = &mut state.paste_preview {
+                preview.enabled.retain(|&t| t != transform);
+                if enabled {
+                    preview.enabled.push(transform);
+                }
+                preview.cleaned = clean_paste(&preview.original, &preview.enabled, preview.markdown);
+            }
+            Task::none()
+        }
+        ResidentMessage::AcceptPaste => match state.paste_preview.take() {
+            Some(preview) => paste_text(state, preview.cleaned),
+            None => Task::none(),
+        },
+        ResidentMessage::PasteOriginal => match state.paste_preview.take() {
+            Some(preview) => paste_text(state, preview.original),
+            None => Task::none(),
+        },
+        ResidentMessage::ClosePastePreview => {
+            state.paste_preview = None;
+            Task::none()
+        }
+        ResidentMessage::ImagePasted(result) => {
+            match result {
+                Ok(Some(path)) => {
+                    logger::log(&format!("[DEBUG app] Saved clipboard image: {}", path.display()));
+                    let reference = format!("{} ", path.display());
+                    let paste = text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(reference)));
+                    state.content.perform(paste);
+                    state.status_message = Some("Image saved".to_string());
+                }
+                Ok(None) => {}
+                Err(e) => state.status_message = Some(e),
+            }
+            Task::none()
+        }
+        ResidentMessage::ExcerptLoaded(path, content) => {
+            match content {
+                Ok(content) => open_excerpt(state, path, content),
+                Err(e) => state.status_message = Some(e),
+            }
+            Task::none()
+        }
+        ResidentMessage::FilesLoaded(files) => {
+            logger::log(&format!("[DEBUG app] Listed {} project files", files.len()));
+            state.files = files;
+            if let Some(picker) = state.picker.as_mut().filter(|p| matches!(p.kind, PickerKind::Files)) {
+                picker.matches = picker_matches(&picker.kind, &state.files, &picker.query);
+            }
+            // Update the popup if an @mention is still being typed
+            let in_mention = text_before_cursor(&state.content)
+                .is_some_and(|prefix| files::mention_query(&prefix).is_some());
+            if in_mention {
+                state.completion = file_completion(state);
+            }
+            Task::none()
+        }
+        ResidentMessage::Submit => {
+            let input_text = take_prompt(state);
+
+            // Answer a pending question instead of pasting into the terminal
+            // (if the asker has gone away, the text goes to the terminal as usual)
+            if !input_text.is_empty() {
+                if let Some((_, reply)) = state.pending_question.take() {
+                    if reply.send(ipc::Response::ok(input_text.clone())) {
+                        state.content = text_editor::Content::new();
+                        state.status_message = Some("Answer sent to Claude".to_string());
+                        return Task::none();
+                    }
+                    logger::log("[DEBUG app] Question was withdrawn, sending to the terminal instead");
+                }
+            }
+
+            if !input_text.is_empty() {
+                if let Err(e) = deliver_to_terminal(&input_text) {
+                    state.status_message = Some(e);
+                    return Task::none();
+                }
+
+                // Clear input
+                state.content = text_editor::Content::new();
+                state.completion = None;
+                state.snippet_session = None;
+                state.status_message = None;
+                state.notification = None;
+            }
+            Task::none()
+        }
+        ResidentMessage::Enqueue => {
+            let input_text = take_prompt(state);
+            if !input_text.is_empty() {
+                state.queue.push(input_text);
+                state.content = text_editor::Content::new();
+                state.completion = None;
+                state.snippet_session = None;
+                state.status_message = Some(format!("Queued ({} waiting)", state.queue.len()));
+            }
+            Task::none()
+        }
+        ResidentMessage::QueueMoveUp(index) => {
+            state.queue.move_up(index);
+            Task::none()
+        }
+        ResidentMessage::QueueMoveDown(index) => {
+            state.queue.move_down(index);
+            Task::none()
+        }
+        ResidentMessage::QueueRemove(index) => {
+            state.queue.remove(index);
+            Task::none()
+        }
+        ResidentMessage::SendQueued => {
+            if let Some(prompt) = state.queue.pop_front() {
+                logger::log(&format!("[DEBUG app] Sending queued prompt, {} left", state.queue.len()));
+                match deliver_to_terminal(&prompt) {
+                    Ok(()) => {
+                        state.status_message = Some(format!("Sent queued prompt ({} waiting)", state.queue.len()));
+                    }
+                    Err(e) => {
+                        // Put it back so it isn't lost
+                        state.queue.push_front(prompt);
+                        state.status_message = Some(e);
+                    }
+                }
+            }
             Task::none()
         }
-        ResidentMessage::Submit => {
-            let input_text = state.content.text();
-            // Normalize line endings: \r\n -> \n, then trim trailing whitespace
-            let input_text = input_text.replace("\r\n", "\n");
-            let input_text = input_text.trim_end();
-            if !input_text.is_empty() {
-                // Write to clipboard
-                if let Err(e) = clipboard_utils::write_to_clipboard(input_text) {
-                    state.status_message = Some(format!("Clipboard error: {}", e));
-                    return Task::none();
+        ResidentMessage::DeliverDeferred(text) => {
+            if let Err(e) = deliver_to_terminal(&text) {
+                // Keep it in the editor so it isn't lost (the user sends it when ready)
+                insert_block(&mut state.content, &text);
+                state.status_message = Some(format!("Template not sent ({}); it is in the editor", e));
+                return focus(EDITOR_ID.clone());
+            }
+            Task::none()
+        }
+        ResidentMessage::QuickAction(index) => {
+            let Some(action) = state.config.quick_actions.get(index) else {
+                return Task::none();
+            };
+            if action.confirm {
+                state.confirming_action = Some(index);
+                Task::none()
+            } else {
+                resident_update(state, ResidentMessage::RunQuickAction(index))
+            }
+        }
+        ResidentMessage::RunQuickAction(index) => {
+            state.confirming_action = None;
+            let Some(action) = state.config.quick_actions.get(index).cloned() else {
+                return Task::none();
+            };
+            // The argument goes through the same path as Submit (normalization, punctuation)
+            let prompt = take_prompt(state);
+            let command = action.command(&prompt);
+            match deliver_to_terminal(&command) {
+                Ok(()) => {
+                    // The editor text was used as the argument, so it's been sent
+                    if action.argument {
+                        state.content = text_editor::Content::new();
+                    }
+                    state.status_message = None;
                 }
-
-                // Paste directly to terminal
-                let hwnd = get_config().and_then(|c| c.terminal_hwnd);
-                logger::log(&format!("[DEBUG app] terminal_hwnd from config: {:?}", hwnd));
-                if let Err(e) = terminal::paste_to_terminal(hwnd) {
-                    state.status_message = Some(format!("Send error: {}", e));
-                    return Task::none();
+                Err(e) => state.status_message = Some(e),
+            }
+            Task::none()
+        }
+        ResidentMessage::CancelQuickAction => {
+            state.confirming_action = None;
+            Task::none()
+        }
+        ResidentMessage::MoveWord { forward, select } => {
+            let cursor = state.content.cursor();
+            let position = cursor.position;
+            let target = state.content.line(position.line).and_then(|line| {
+                if forward {
+                    words::next_boundary(&line.text, position.column)
+                } else {
+                    words::previous_boundary(&line.text, position.column)
                 }
-
-                // Clear input
-                state.content = text_editor::Content::new();
-                state.status_message = None;
+            });
+            let Some(column) = target else {
+                // Latin text, code and line ends keep the editor's own word motion
+                let motion = if forward {
+                    text_editor::Motion::WordRight
+                } else {
+                    text_editor::Motion::WordLeft
+                };
+                let action = if select {
+                    text_editor::Action::Select(motion)
+                } else {
+                    text_editor::Action::Move(motion)
+                };
+                return resident_update(state, ResidentMessage::EditorAction(action));
+            };
+            let target = text_editor::Position {
+                line: position.line,
+                column,
+            };
+            let anchor = if select { cursor.selection.unwrap_or(position) } else { target };
+            select_range(&mut state.content, anchor, target);
+            state.completion = None;
+            Task::none()
+        }
+        ResidentMessage::DeleteWordBackward => {
+            let cursor = state.content.cursor();
+            let position = cursor.position;
+            let start = state
+                .content
+                .line(position.line)
+                .and_then(|line| words::previous_boundary(&line.text, position.column))
+                .filter(|_| cursor.selection.is_none());
+            if let Some(column) = start {
+                let start = text_editor::Position {
+                    line: position.line,
+                    column,
+                };
+                select_range(&mut state.content, start, position);
+            }
+            let backspace = text_editor::Action::Edit(text_editor::Edit::Backspace);
+            resident_update(state, ResidentMessage::EditorAction(backspace))
+        }
+        ResidentMessage::ToggleRomaji => {
+            let task = commit_preedit(state);
+            state.romaji_mode = state.romaji_mode.next();
+            logger::log(&format!("[DEBUG app] Romaji input: {:?}", state.romaji_mode));
+            task
+        }
+        ResidentMessage::RomajiInput(c) => {
+            // Typing on after choosing a candidate accepts it
+            let mut task = Task::none();
+            if state.preedit.selected.is_some() {
+                task = commit_preedit(state);
+            }
+            if c.is_ascii_alphabetic() || c == '\'' || c == '-' {
+                state.preedit.romaji.push(c);
+                state.preedit.candidates.clear();
+                return task;
+            }
+            // Punctuation ends the phrase; anything else (digits, symbols) is typed as is
+            let punctuation = ",.[]/~".contains(c);
+            if punctuation {
+                state.preedit.romaji.push(c);
+            }
+            let commit = commit_preedit(state);
+            let typed = if punctuation {
+                Task::none()
+            } else {
+                paste_text(state, c.to_string())
+            };
+            Task::batch([task, commit, typed])
+        }
+        ResidentMessage::RomajiConvert => {
+            if !state.preedit.next_candidate(&state.config.kana_dictionary) {
+                return commit_preedit(state);
+            }
+            Task::none()
+        }
+        ResidentMessage::RomajiBackspace => {
+            state.preedit.backspace();
+            Task::none()
+        }
+        ResidentMessage::RomajiCommit => commit_preedit(state),
+        ResidentMessage::RomajiCancel => {
+            state.preedit = Preedit::default();
+            Task::none()
+        }
+        ResidentMessage::ToggleForwarding => {
+            state.forwarding = !state.forwarding;
+            Task::none()
+        }
+        ResidentMessage::ForwardKey(key) => {
+            // Sending waits for Alt to be released and for the focus change, so not on the UI thread
+            let hwnd = get_config().and_then(|c| c.terminal_hwnd);
+            let (sender, receiver) = iced::futures::channel::oneshot::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(terminal::send_key_to_terminal(hwnd, key));
+            });
+            Task::perform(receiver, |result| {
+                ResidentMessage::KeyForwarded(result.unwrap_or_else(|_| Err("Key forwarding was cancelled".to_string())))
+            })
+        }
+        ResidentMessage::KeyForwarded(result) => {
+            if let Err(e) = result {
+                state.status_message = Some(format!("Send error: {}", e));
+            }
+            hotkey::focus_own_window();
+            focus(EDITOR_ID.clone())
+        }
+        ResidentMessage::DismissNotification => {
+            state.notification = None;
+            Task::none()
+        }
+        ResidentMessage::DismissQuestion => {
+            if let Some((_, reply)) = state.pending_question.take() {
+                reply.send(ipc::Response::error("The user dismissed the question without answering"));
             }
             Task::none()
         }
+        ResidentMessage::FlashTick => {
+            state.flash_remaining = state.flash_remaining.saturating_sub(1);
+            if state.flash_remaining == 0 {
+                state.flash_on = false;
+                Task::none()
+            } else {
+                state.flash_on = !state.flash_on;
+                after(FLASH_INTERVAL, ResidentMessage::FlashTick)
+            }
+        }
+        ResidentMessage::Ipc(incoming) => handle_ipc(state, incoming),
         ResidentMessage::Event(event) => {
             // Auto-focus the text editor when window gains focus
             if let Event::Window(window::Event::Focused) = event {
@@ -150,6 +1734,45 @@ fn resident_update(state: &mut ResidentClaudeInput, message: ResidentMessage) ->
                 return focus(EDITOR_ID.clone());
             }
 
+            // A file dropped on the window: choose lines to insert
+            if let Event::Window(window::Event::FileDropped(path)) = event {
+                logger::log(&format!("[DEBUG app] File dropped: {}", path.display()));
+                return load_excerpt(path);
+            }
+
+            // Picker: ↑/↓ move (the search box ignores them), Esc closes
+            // Esc is handled on release because the search box captures the press
+            if state.picker.is_some() {
+                match &event {
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: Key::Named(keyboard::key::Named::ArrowUp),
+                        ..
+                    }) => return resident_update(state, ResidentMessage::PickerMove(-1)),
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: Key::Named(keyboard::key::Named::ArrowDown),
+                        ..
+                    }) => return resident_update(state, ResidentMessage::PickerMove(1)),
+                    Event::Keyboard(keyboard::Event::KeyReleased {
+                        key: Key::Named(keyboard::key::Named::Escape),
+                        ..
+                    }) => return resident_update(state, ResidentMessage::ClosePicker),
+                    _ => {}
+                }
+            } else if state.excerpt.is_some() || state.paste_preview.is_some() {
+                if let Event::Keyboard(keyboard::Event::KeyReleased {
+                    key: Key::Named(keyboard::key::Named::Escape),
+                    ..
+                }) = event
+                {
+                    let message = if state.excerpt.is_some() {
+                        ResidentMessage::CloseExcerpt
+                    } else {
+                        ResidentMessage::ClosePastePreview
+                    };
+                    return resident_update(state, message);
+                }
+            }
+
             // Handle Ctrl+I to toggle focus back to terminal
             if let Event::Keyboard(keyboard::Event::KeyPressed {
                 key: Key::Character(c),
@@ -164,7 +1787,7 @@ fn resident_update(state: &mut ResidentClaudeInput, message: ResidentMessage) ->
                 }
             }
 
-            // Handle Ctrl+Enter to send
+            // Handle Ctrl+Enter to send, Ctrl+Shift+Enter to enqueue
             // Note: We use KeyReleased because text_editor consumes KeyPressed for Enter
             if let Event::Keyboard(keyboard::Event::KeyReleased {
                 key: Key::Named(keyboard::key::Named::Enter),
@@ -172,6 +1795,10 @@ fn resident_update(state: &mut ResidentClaudeInput, message: ResidentMessage) ->
                 ..
             }) = event
             {
+                if modifiers.control() && modifiers.shift() {
+                    logger::log("[DEBUG app] Ctrl+Shift+Enter released, enqueueing");
+                    return resident_update(state, ResidentMessage::Enqueue);
+                }
                 if modifiers.control() {
                     logger::log("[DEBUG app] Ctrl+Enter released, submitting");
                     return resident_update(state, ResidentMessage::Submit);
@@ -182,23 +1809,327 @@ fn resident_update(state: &mut ResidentClaudeInput, message: ResidentMessage) ->
     }
 }
 
+/// Key to forward for an Alt shortcut (Alt+1..9, Alt+Esc, Alt+↑/↓, Alt+Enter, Alt+M for Shift+Tab)
+fn forward_key_for(key_press: &text_editor::KeyPress) -> Option<ForwardKey> {
+    match key_press.key.as_ref() {
+        Key::Named(keyboard::key::Named::Escape) => Some(ForwardKey::Escape),
+        Key::Named(keyboard::key::Named::ArrowUp) => Some(ForwardKey::Up),
+        Key::Named(keyboard::key::Named::ArrowDown) => Some(ForwardKey::Down),
+        Key::Named(keyboard::key::Named::Enter) => Some(ForwardKey::Enter),
+        _ => match key_press.key.to_latin(key_press.physical_key)? {
+            'm' => Some(ForwardKey::ShiftTab),
+            c @ '1'..='9' => c.to_digit(10).map(|d| ForwardKey::Digit(d as u8)),
+            _ => None,
+        },
+    }
+}
+
+/// Popup navigation while completions are shown (↑/↓ select, Tab/Enter accept, Esc close)
+/// Enter falls through to the editor for completions that don't accept it
+fn completion_key_binding(completion: &Completion, key_press: &text_editor::KeyPress) -> Option<ResidentMessage> {
+    use keyboard::key::Named;
+
+    if key_press.modifiers.control() || key_press.modifiers.alt() {
+        return None;
+    }
+    match key_press.key.as_ref() {
+        Key::Named(Named::ArrowUp) if completion.captures_keys => Some(ResidentMessage::CompletionMove(-1)),
+        Key::Named(Named::ArrowDown) if completion.captures_keys => Some(ResidentMessage::CompletionMove(1)),
+        Key::Named(Named::Tab) => Some(ResidentMessage::AcceptCompletion(completion.selected)),
+        Key::Named(Named::Enter) if completion.captures_keys => {
+            Some(ResidentMessage::AcceptCompletion(completion.selected))
+        }
+        Key::Named(Named::Escape) => Some(ResidentMessage::CloseCompletion),
+        _ => None,
+    }
+}
+
+/// Editor state the key bindings depend on (captured when the view is built)
+#[derive(Clone, Copy)]
+struct KeyContext<'a> {
+    completion: Option<&'a Completion>,
+    in_snippet: bool,
+    /// An abbreviation ends right before the cursor
+    abbreviation_ready: bool,
+    can_undo_expansion: bool,
+    romaji_mode: romaji::Mode,
+    /// Romaji is being composed (Space, Enter, Backspace and Esc act on it)
+    composing: bool,
+}
+
+/// Keys captured by the romaji input mode: printable characters, and the keys that act on
+/// the composed text while there is some
+fn romaji_key_binding(composing: bool, key_press: &text_editor::KeyPress) -> Option<ResidentMessage> {
+    use keyboard::key::Named;
+
+    match key_press.key.as_ref() {
+        Key::Named(Named::Space) if composing => Some(ResidentMessage::RomajiConvert),
+        Key::Named(Named::Backspace) if composing => Some(ResidentMessage::RomajiBackspace),
+        Key::Named(Named::Enter) if composing => Some(ResidentMessage::RomajiCommit),
+        Key::Named(Named::Escape) if composing => Some(ResidentMessage::RomajiCancel),
+        _ => {
+            let mut chars = key_press.text.as_deref()?.chars();
+            let c = chars.next().filter(|c| c.is_ascii_graphic() && chars.next().is_none())?;
+            Some(ResidentMessage::RomajiInput(c))
+        }
+    }
+}
+
+/// Editor key bindings: resident shortcuts first, then iced's defaults
+fn editor_key_binding(
+    context: KeyContext<'_>,
+    key_press: text_editor::KeyPress,
+) -> Option<text_editor::Binding<ResidentMessage>> {
+    use text_editor::Binding;
+
+    if matches!(key_press.status, text_editor::Status::Focused { .. }) {
+        if let Some(message) = context.completion.and_then(|c| completion_key_binding(c, &key_press)) {
+            return Some(Binding::Custom(message));
+        }
+        let modifiers = key_press.modifiers;
+        if context.romaji_mode != romaji::Mode::Off && !modifiers.control() && !modifiers.alt() {
+            if let Some(message) = romaji_key_binding(context.composing, &key_press) {
+                return Some(Binding::Custom(message));
+            }
+        }
+        let plain = !modifiers.control() && !modifiers.alt() && !modifiers.shift();
+        if plain && matches!(key_press.key.as_ref(), Key::Named(keyboard::key::Named::Tab)) {
+            if context.in_snippet {
+                return Some(Binding::Custom(ResidentMessage::NextPlaceholder));
+            }
+            if context.abbreviation_ready {
+                return Some(Binding::Custom(ResidentMessage::ExpandAbbreviation));
+            }
+        }
+        let latin = key_press.key.to_latin(key_press.physical_key);
+        if modifiers.control() && latin == Some('g') {
+            return Some(Binding::Custom(ResidentMessage::ToggleGitPanel));
+        }
+        if modifiers.control() && latin == Some('t') {
+            return Some(Binding::Custom(ResidentMessage::OpenSnippets));
+        }
+        if modifiers.control() && latin == Some('o') {
+            return Some(Binding::Custom(ResidentMessage::OpenFilePicker));
+        }
+        if modifiers.control() && !modifiers.alt() && latin == Some('v') {
+            let message = if modifiers.shift() {
+                ResidentMessage::PastePlainText
+            } else {
+                ResidentMessage::PasteClipboard
+            };
+            return Some(Binding::Custom(message));
+        }
+        if modifiers.control() && matches!(key_press.key.as_ref(), Key::Character(".")) {
+            return Some(Binding::Custom(ResidentMessage::SuggestSpelling));
+        }
+        if modifiers.control() && latin == Some('p') {
+            return Some(Binding::Custom(ResidentMessage::ConvertPunctuation));
+        }
+        if modifiers.control() && latin == Some('z') && context.can_undo_expansion {
+            return Some(Binding::Custom(ResidentMessage::UndoExpansion));
+        }
+        if modifiers.alt() && !modifiers.control() {
+            if let Some(key) = forward_key_for(&key_press) {
+                return Some(Binding::Custom(ResidentMessage::ForwardKey(key)));
+            }
+        }
+        if modifiers.control() && !modifiers.alt() {
+            let forward = match key_press.key.as_ref() {
+                Key::Named(keyboard::key::Named::ArrowLeft) => Some(false),
+                Key::Named(keyboard::key::Named::ArrowRight) => Some(true),
+                _ => None,
+            };
+            if let Some(forward) = forward {
+                let select = modifiers.shift();
+                return Some(Binding::Custom(ResidentMessage::MoveWord { forward, select }));
+            }
+            if matches!(key_press.key.as_ref(), Key::Named(keyboard::key::Named::Backspace)) {
+                return Some(Binding::Custom(ResidentMessage::DeleteWordBackward));
+            }
+        }
+        if modifiers.control() && latin == Some('j') {
+            return Some(Binding::Custom(ResidentMessage::ToggleRomaji));
+        }
+        if modifiers.control() && latin == Some('k') {
+            return Some(Binding::Custom(ResidentMessage::ToggleForwarding));
+        }
+    }
+
+    Binding::from_key_press(key_press)
+}
+
+/// Toolbar with the configured quick actions (or a confirmation prompt for one of them)
+fn quick_action_toolbar(state: &ResidentClaudeInput) -> Element<'_, ResidentMessage> {
+    let small_button = |label: String, message: ResidentMessage| {
+        button(text(label).size(11))
+            .padding([2, 8])
+            .style(button::secondary)
+            .on_press(message)
+    };
+
+    let toolbar = match state.confirming_action.and_then(|i| state.config.quick_actions.get(i).map(|a| (i, a))) {
+        Some((index, action)) => row![
+            text(format!("Send {}?", action.command(&editor_text(state))))
+                .size(11)
+                .color(Color::from_rgb8(249, 226, 175)), // Yellow
+            small_button("Send".to_string(), ResidentMessage::RunQuickAction(index)),
+            small_button("Cancel".to_string(), ResidentMessage::CancelQuickAction),
+        ]
+        .spacing(4),
+        None => row(state.config.quick_actions.iter().enumerate().map(|(index, action)| {
+            small_button(action.label.clone(), ResidentMessage::QuickAction(index)).into()
+        }))
+        .spacing(4),
+    };
+
+    container(toolbar).padding([0, 8]).into()
+}
+
+/// Buttons for the git context to insert, or the hunk list while picking hunks
+fn git_panel(panel: &GitPanel) -> Element<'_, ResidentMessage> {
+    let small_button = |label: String, message: ResidentMessage| {
+        button(text(label).size(11))
+            .padding([2, 8])
+            .style(button::secondary)
+            .on_press(message)
+    };
+
+    let Some(ref hunks) = panel.hunks else {
+        let buttons = GitContext::ALL
+            .into_iter()
+            .map(|context| small_button(context.label().to_string(), ResidentMessage::InsertGitContext(context)).into())
+            .chain(std::iter::once(small_button("×".to_string(), ResidentMessage::ToggleGitPanel).into()));
+        return container(row(buttons).spacing(4)).padding([0, 8]).into();
+    };
+
+    let list = hunks.iter().enumerate().map(|(index, (hunk, chosen))| {
+        checkbox(*chosen)
+            .label(format!("{}  {}", hunk.file, hunk.header))
+            .text_size(11)
+            .size(12)
+            .on_toggle(move |chosen| ResidentMessage::ToggleHunk(index, chosen))
+            .into()
+    });
+    let chosen = hunks.iter().filter(|(_, c)| *c).count();
+    let actions = row![
+        small_button(format!("Insert {} hunks", chosen), ResidentMessage::InsertHunks),
+        small_button("Cancel".to_string(), ResidentMessage::ToggleGitPanel),
+    ]
+    .spacing(4);
+
+    let list = container(scrollable(column(list).spacing(2))).max_height(90);
+    container(column![list, actions].spacing(4))
+        .padding([0, 8])
+        .into()
+}
+
+/// Transform toggles and the cleaned-up text, before pasting
+fn paste_preview_panel(preview: &PastePreview) -> Element<'_, ResidentMessage> {
+    let small_button = |label: &'static str, message: ResidentMessage| {
+        button(text(label).size(11))
+            .padding([2, 8])
+            .style(button::secondary)
+            .on_press(message)
+    };
+
+    let toggles = PasteTransform::ALL.into_iter().map(|transform| {
+        checkbox(preview.enabled.contains(&transform))
+            .label(transform.label())
+            .text_size(11)
+            .size(12)
+            .on_toggle(move |enabled| ResidentMessage::TogglePasteTransform(transform, enabled))
+            .into()
+    });
+    let cleaned = text(&preview.cleaned)
+        .size(11)
+        .font(iced::Font::MONOSPACE)
+        .color(Color::from_rgb8(205, 214, 244)); // Text
+    let cleaned = container(scrollable(cleaned))
+        .max_height(140)
+        .width(Length::Fill)
+        .padding(4)
+        .style(|_theme| container::Style {
+            background: Some(Color::from_rgb8(24, 24, 37).into()), // Mantle
+            ..Default::default()
+        });
+    let actions = row![
+        small_button("Paste", ResidentMessage::AcceptPaste),
+        small_button("Paste original", ResidentMessage::PasteOriginal),
+        small_button("Cancel", ResidentMessage::ClosePastePreview),
+    ]
+    .spacing(4);
+
+    container(column![row(toggles).spacing(8), cleaned, actions].spacing(4))
+        .padding([0, 8])
+        .into()
+}
+
+/// Toolbar with the keys Claude's interactive menus wait for
+fn forwarding_toolbar() -> Element<'static, ResidentMessage> {
+    let keys = [
+        ForwardKey::Escape,
+        ForwardKey::ShiftTab,
+        ForwardKey::Up,
+        ForwardKey::Down,
+        ForwardKey::Enter,
+    ]
+    .into_iter()
+    .chain((1..=9).map(ForwardKey::Digit));
+
+    let buttons = keys.map(|key| {
+        button(text(key.label()).size(11))
+            .padding([2, 6])
+            .style(button::secondary)
+            .on_press(ResidentMessage::ForwardKey(key))
+            .into()
+    });
+
+    container(row(buttons).spacing(4))
+        .padding([0, 8])
+        .into()
+}
+
 fn resident_view(state: &ResidentClaudeInput) -> Element<'_, ResidentMessage> {
     // Get accent color from config (falls back to Lavender if not set)
     let accent_color = get_config()
         .map(|c| c.accent_color)
         .unwrap_or(Color::from_rgb8(180, 190, 254));
 
+    let flash_on = state.flash_on;
+    let key_context = KeyContext {
+        completion: state.completion.as_ref(),
+        in_snippet: state.snippet_session.is_some(),
+        abbreviation_ready: !state.config.abbreviations.is_empty()
+            && text_before_cursor(&state.content)
+                .is_some_and(|p| abbreviations::find(&p, &state.config.abbreviations).is_some()),
+        can_undo_expansion: state.last_expansion.is_some(),
+        romaji_mode: state.romaji_mode,
+        composing: !state.preedit.is_empty(),
+    };
+
     // Text editor with Catppuccin Mocha styling
     // Border color changes based on focus status (uses instance-specific accent color)
+    // Proofreading issues are colored in the editor and listed beside it
+    let issues = &state.issues;
+    let mut marks: Vec<Vec<(Range<usize>, Rule)>> = Vec::new();
+    for issue in issues {
+        if marks.len() <= issue.line {
+            marks.resize(issue.line + 1, Vec::new());
+        }
+        marks[issue.line].push((issue.range.clone(), issue.rule));
+    }
+
     let editor = text_editor(&state.content)
         .id(EDITOR_ID.clone())
-        .placeholder("Ctrl+I: Toggle | Ctrl+Enter: Send")
+        .placeholder("Ctrl+I: Toggle | Ctrl+Enter: Send | Ctrl+Shift+Enter: Queue")
         .on_action(ResidentMessage::EditorAction)
+        .key_binding(move |key_press| editor_key_binding(key_context, key_press))
         .height(Length::Fill)
         .padding(10)
         .style(move |_theme: &Theme, status| {
             // Bright border when focused (accent color), dim when not (darkened accent)
             let (border_color, border_width) = match status {
+                _ if flash_on => (accent_color, 6.0),  // Notification flash
                 text_editor::Status::Focused { .. } => (accent_color, 4.0),  // Bright, thick
                 _ => {
                     // Non-focused: use darkened accent color (50% brightness)
@@ -217,27 +2148,113 @@ fn resident_view(state: &ResidentClaudeInput) -> Element<'_, ResidentMessage> {
                 value: Color::from_rgb8(205, 214, 244),       // Text
                 selection: Color::from_rgba8(137, 180, 250, 0.4),  // Blue with 40% opacity
             }
+        })
+        .highlight_with::<LintHighlighter>(marks, |rule, _theme| iced::advanced::text::highlighter::Format {
+            color: Some(match rule {
+                Rule::Spelling => Color::from_rgb8(203, 166, 247), // Mauve
+                _ => Color::from_rgb8(243, 139, 168),              // Red
+            }),
+            font: None,
         });
 
+    let mut content = column![].spacing(4).padding(8);
+
+    // Notification banner from Claude (e.g. waiting for permission)
+    if let Some(ref notification) = state.notification {
+        let banner = row![
+            text(notification)
+                .size(12)
+                .color(Color::from_rgb8(249, 226, 175)) // Yellow
+                .width(Length::Fill),
+            button(text("×").size(10))
+                .padding([0, 6])
+                .style(button::secondary)
+                .on_press(ResidentMessage::DismissNotification),
+        ]
+        .spacing(4);
+        content = content.push(container(banner).padding([2, 8]));
+    }
+
+    // Question from Claude (MCP ask_user), answered with Ctrl+Enter
+    if let Some((ref question, _)) = state.pending_question {
+        let banner = row![
+            column![
+                text("Claude asks (Ctrl+Enter to answer):")
+                    .size(11)
+                    .color(Color::from_rgb8(108, 112, 134)), // Overlay0
+                text(question).size(13).color(Color::from_rgb8(137, 180, 250)), // Blue
+            ]
+            .width(Length::Fill),
+            button(text("×").size(10))
+                .padding([0, 6])
+                .style(button::secondary)
+                .on_press(ResidentMessage::DismissQuestion),
+        ]
+        .spacing(4);
+        content = content.push(container(scrollable(banner)).max_height(120).padding([2, 8]));
+    }
+
+    // Quick actions (slash commands etc. from config)
+    if !state.config.quick_actions.is_empty() {
+        content = content.push(quick_action_toolbar(state));
+    }
+
+    // Insert git context (Ctrl+G)
+    if let Some(ref panel) = state.git_panel {
+        content = content.push(git_panel(panel));
+    }
+
+    // Key-forwarding toolbar (Ctrl+K)
+    if state.forwarding {
+        content = content.push(forwarding_toolbar());
+    }
+
+    // Snippet/file picker, excerpt range, paste preview, or the autocomplete popup for the token before the cursor
+    if let Some(ref picker) = state.picker {
+        content = content.push(picker_panel(picker));
+    } else if let Some(ref excerpt) = state.excerpt {
+        content = content.push(excerpt_panel(excerpt));
+    } else if let Some(ref preview) = state.paste_preview {
+        content = content.push(paste_preview_panel(preview));
+    } else if let Some(ref completion) = state.completion {
+        content = content.push(completion_popup(completion, ResidentMessage::AcceptCompletion));
+    } else if let Some(hint) = argument_hint(state) {
+        content = content.push(
+            container(text(hint).size(11).color(Color::from_rgb8(108, 112, 134))) // Overlay0
+                .padding([0, 8]),
+        );
+    }
+
+    if issues.is_empty() {
+        content = content.push(editor);
+    } else {
+        content = content.push(row![editor, lint_panel(issues)].spacing(4));
+    }
+
+    // Romaji input indicator with the text being composed
+    if state.romaji_mode != romaji::Mode::Off {
+        content = content.push(romaji_bar(state.romaji_mode, &state.preedit));
+    }
+
+    // Queue panel (only show if prompts are waiting)
+    if !state.queue.is_empty() {
+        content = content.push(queue_panel(&state.queue));
+    }
+
     // Status message (only show if there's a message)
-    let content: Element<'_, ResidentMessage> = if let Some(ref msg) = state.status_message {
+    if let Some(ref msg) = state.status_message {
         let status_text = if msg.contains("error") || msg.contains("Error") {
             text(msg).size(11).color(Color::from_rgb8(243, 139, 168))  // Red
         } else {
             text(msg).size(11).color(Color::from_rgb8(166, 227, 161))  // Green
         };
-        column![
-            editor,
-            container(status_text).padding([2, 8]),
-        ]
-        .spacing(4)
-        .padding(8)
-        .into()
-    } else {
-        container(editor)
-            .padding(8)
-            .into()
-    };
+        content = content.push(container(status_text).padding([2, 8]));
+    }
+
+    // Footer mirrored from the statusLine command
+    if let Some(ref info) = state.status_line {
+        content = content.push(status_footer(info));
+    }
 
     container(content)
         .width(Length::Fill)
@@ -249,10 +2266,265 @@ fn resident_view(state: &ResidentClaudeInput) -> Element<'_, ResidentMessage> {
         .into()
 }
 
+/// Search box and matching snippets or files
+fn picker_panel(picker: &Picker) -> Element<'_, ResidentMessage> {
+    let selected = picker.matches.as_ref().map_or(0, |m| m.selected);
+    let placeholder = match picker.kind {
+        PickerKind::Snippets(_) => "Snippet (↑↓: Select | Enter: Insert | Esc: Close)",
+        PickerKind::Files => "File excerpt (↑↓: Select | Enter: Choose lines | Esc: Close)",
+    };
+    let search = text_input(placeholder, &picker.query)
+        .id(PICKER_SEARCH_ID.clone())
+        .on_input(ResidentMessage::PickerQueryChanged)
+        .on_submit(ResidentMessage::PickerSelect(selected))
+        .size(12)
+        .padding([2, 6]);
+
+    let mut picker_column = column![search].spacing(2);
+    if let Some(ref matches) = picker.matches {
+        picker_column = picker_column.push(completion_popup(matches, ResidentMessage::PickerSelect));
+    }
+    container(picker_column).padding([0, 8]).into()
+}
+
+/// Line range inputs for a file excerpt, with a warning for large or binary files
+fn excerpt_panel(excerpt: &Excerpt) -> Element<'_, ResidentMessage> {
+    let content = &excerpt.content;
+    let warning = if content.binary {
+        Some("Binary file: it can't be inserted as text".to_string())
+    } else if content.size > files::LARGE_FILE_BYTES {
+        Some(format!(
+            "Large file ({} KB, {} lines): consider a smaller range",
+            content.size / 1024,
+            content.line_count
+        ))
+    } else {
+        None
+    };
+
+    let line_input = |value: &str, on_input: fn(String) -> ResidentMessage| {
+        text_input("line", value)
+            .on_input(on_input)
+            .on_submit(ResidentMessage::InsertExcerpt)
+            .size(12)
+            .width(60)
+            .padding([2, 6])
+    };
+    let mut insert_button = button(text("Insert").size(11)).padding([2, 8]).style(button::secondary);
+    if !content.binary {
+        insert_button = insert_button.on_press(ResidentMessage::InsertExcerpt);
+    }
+
+    let range = row![
+        text(&excerpt.display)
+            .size(11)
+            .color(Color::from_rgb8(205, 214, 244)) // Text
+            .width(Length::Fill),
+        line_input(&excerpt.start, ResidentMessage::ExcerptStartChanged),
+        text("-").size(11),
+        line_input(&excerpt.end, ResidentMessage::ExcerptEndChanged),
+        text(format!("/ {}", content.line_count))
+            .size(11)
+            .color(Color::from_rgb8(108, 112, 134)), // Overlay0
+        insert_button,
+        button(text("×").size(11))
+            .padding([2, 8])
+            .style(button::secondary)
+            .on_press(ResidentMessage::CloseExcerpt),
+    ]
+    .spacing(4)
+    .align_y(iced::Alignment::Center);
+
+    let mut panel = column![range].spacing(2);
+    if let Some(warning) = warning {
+        panel = panel.push(text(warning).size(11).color(Color::from_rgb8(249, 226, 175))); // Yellow
+    }
+    container(panel).padding([0, 8]).into()
+}
+
+/// Completion candidates; the selected one is highlighted
+fn completion_popup(
+    completion: &Completion,
+    on_select: fn(usize) -> ResidentMessage,
+) -> Element<'_, ResidentMessage> {
+    let rows = completion.items.iter().enumerate().map(|(index, item)| {
+        let selected = index == completion.selected;
+        let entry = row![
+            text(&item.label).size(11).color(Color::from_rgb8(205, 214, 244)), // Text
+            text(&item.detail)
+                .size(11)
+                .color(Color::from_rgb8(108, 112, 134)) // Overlay0
+                .wrapping(text::Wrapping::None),
+        ]
+        .spacing(10);
+        button(entry)
+            .padding([1, 6])
+            .width(Length::Fill)
+            .style(move |theme: &Theme, status| {
+                if selected {
+                    button::Style {
+                        background: Some(Background::Color(Color::from_rgb8(69, 71, 90))), // Surface1
+                        border: Border {
+                            radius: 4.0.into(),
+                            ..Default::default()
+                        },
+                        ..button::text(theme, status)
+                    }
+                } else {
+                    button::text(theme, status)
+                }
+            })
+            .on_press(on_select(index))
+            .into()
+    });
+
+    container(column(rows))
+        .padding(2)
+        .width(Length::Fill)
+        .clip(true)
+        .style(|_theme: &Theme| container::Style {
+            background: Some(Background::Color(Color::from_rgb8(24, 24, 37))), // Mantle
+            border: Border {
+                radius: 6.0.into(),
+                width: 1.0,
+                color: Color::from_rgb8(69, 71, 90), // Surface1
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+/// Footer with model, directory, cost and context usage
+fn status_footer(info: &ipc::StatusLineInfo) -> Element<'_, ResidentMessage> {
+    let dim = Color::from_rgb8(108, 112, 134); // Overlay0
+    let mut footer = row![
+        text(&info.model).size(11).color(Color::from_rgb8(203, 166, 247)), // Mauve
+        text(statusline::dir_name(&info.cwd)).size(11).color(dim),
+        text(format!("${:.2}", info.cost_usd)).size(11).color(dim),
+    ]
+    .spacing(10);
+
+    if let Some(percent) = info.context_percent {
+        // Context pressure: green < 50%, yellow < 80%, red otherwise
+        let color = match percent {
+            0..50 => Color::from_rgb8(166, 227, 161),  // Green
+            50..80 => Color::from_rgb8(249, 226, 175), // Yellow
+            _ => Color::from_rgb8(243, 139, 168),      // Red
+        };
+        footer = footer.push(text(format!("ctx {}%", percent)).size(11).color(color));
+    }
+
+    container(footer).padding([0, 8]).into()
+}
+
+/// Romaji input mode indicator; the composed text is underlined like an IME's
+fn romaji_bar(mode: romaji::Mode, preedit: &Preedit) -> Element<'static, ResidentMessage> {
+    let mode_label = match mode {
+        romaji::Mode::Katakana => "カタカナ",
+        _ => "ひらがな",
+    };
+    let mut bar = row![
+        text(format!("[{}] {} (Ctrl+J)", mode.label(), mode_label))
+            .size(11)
+            .color(Color::from_rgb8(203, 166, 247)), // Mauve
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center);
+
+    if !preedit.is_empty() {
+        let composed = match preedit.selected {
+            Some(_) => preedit.text(mode),
+            None => preedit.kana(mode),
+        };
+        bar = bar.push(
+            rich_text([span(composed)
+                .underline(true)
+                .color(Color::from_rgb8(205, 214, 244))]) // Text
+            .on_link_click(iced::never)
+            .size(14),
+        );
+        let hint = match preedit.selected {
+            Some(index) => format!("{}/{} (Space: Next | Enter: Commit)", index + 1, preedit.candidates.len()),
+            None => "Space: Convert | Enter: Commit | Esc: Cancel".to_string(),
+        };
+        bar = bar.push(text(hint).size(10).color(Color::from_rgb8(108, 112, 134))); // Overlay0
+    }
+    container(bar).padding([0, 8]).into()
+}
+
+/// Proofreading issues: click one to select it, "Fix" applies the suggested replacement
+fn lint_panel(issues: &[Issue]) -> Element<'static, ResidentMessage> {
+    let rows = issues.iter().map(|issue| {
+        let message = button(text(issue.message.clone()).size(10).color(Color::from_rgb8(250, 179, 135))) // Peach
+            .padding([1, 4])
+            .style(button::text)
+            .on_press(ResidentMessage::SelectIssue(issue.clone()))
+            .width(Length::Fill);
+        let mut line = row![message].spacing(2).align_y(iced::Alignment::Center);
+        if issue.fix.is_some() {
+            line = line.push(
+                button(text("Fix").size(10))
+                    .padding([0, 6])
+                    .style(button::secondary)
+                    .on_press(ResidentMessage::FixIssue(issue.clone())),
+            );
+        }
+        line.into()
+    });
+
+    container(scrollable(column(rows).spacing(2)))
+        .width(170)
+        .height(Length::Fill)
+        .padding(2)
+        .style(|_theme| container::Style {
+            background: Some(Color::from_rgb8(24, 24, 37).into()), // Mantle
+            ..Default::default()
+        })
+        .into()
+}
+
+/// List of queued prompts with reorder/remove buttons
+fn queue_panel(queue: &PromptQueue) -> Element<'_, ResidentMessage> {
+    let small_button = |label: &'static str, message: ResidentMessage| {
+        button(text(label).size(10))
+            .padding([0, 6])
+            .style(button::secondary)
+            .on_press(message)
+    };
+
+    let rows = queue.items().iter().enumerate().map(|(index, item)| {
+        // First line only, truncated to keep rows single-line
+        let first_line = item.lines().next().unwrap_or_default();
+        let mut preview: String = first_line.chars().take(60).collect();
+        if preview.len() < first_line.len() || item.lines().count() > 1 {
+            preview.push('…');
+        }
+        row![
+            text(format!("{}. {}", index + 1, preview))
+                .size(11)
+                .color(Color::from_rgb8(186, 194, 222)) // Subtext1
+                .width(Length::Fill),
+            small_button("▲", ResidentMessage::QueueMoveUp(index)),
+            small_button("▼", ResidentMessage::QueueMoveDown(index)),
+            small_button("×", ResidentMessage::QueueRemove(index)),
+        ]
+        .spacing(4)
+        .into()
+    });
+
+    container(scrollable(column(rows).spacing(2)))
+        .max_height(80)
+        .padding([2, 8])
+        .into()
+}
+
 fn resident_subscription(_state: &ResidentClaudeInput) -> Subscription<ResidentMessage> {
     // Note: Pulse animation disabled for now (time::every not available in iced 0.14)
     // Just use static highlight when typing - can add animation later
-    event::listen().map(ResidentMessage::Event)
+    Subscription::batch([
+        event::listen().map(ResidentMessage::Event),
+        Subscription::run(ipc::listen).map(ResidentMessage::Ipc),
+    ])
 }
 
 /// Register own MojiBridge hwnd asynchronously (polls until window is found)
@@ -285,11 +2557,37 @@ pub fn run_resident_gui(config: ResidentConfig) -> iced::Result {
     // Display title includes random name for user visibility
     let display_title = format!("{} | {}", internal_title, random_name);
 
+    // Bind the IPC listener and register this instance so hooks can find it
+    let instance = match ipc::bind() {
+        Ok((port, token)) => {
+            let instance = ipc::InstanceInfo {
+                terminal_hwnd: hwnd,
+                name: random_name.clone(),
+                label: config.label.clone(),
+                port,
+                token,
+                pid: std::process::id(),
+                sessions: config.session.clone().into_iter().collect(),
+            };
+            if let Err(e) = ipc::register_instance(&instance) {
+                logger::log(&format!("[DEBUG app] {}", e));
+            }
+            Some(instance)
+        }
+        Err(e) => {
+            logger::log(&format!("[DEBUG app] {}", e));
+            None
+        }
+    };
+
     // Store config globally (OnceLock ensures thread-safe one-time initialization)
     let _ = RESIDENT_CONFIG.set(ResidentConfigData {
         terminal_hwnd: config.terminal_hwnd,
         window_title: internal_title.clone(),
         accent_color,
+        cwd: config.cwd,
+        transcript: config.transcript,
+        instance,
     });
 
     // Start async hwnd registration (polls until window is created)
@@ -308,12 +2606,12 @@ pub fn run_resident_gui(config: ResidentConfig) -> iced::Result {
 
     iced::application(
         || (ResidentClaudeInput::default(), Task::none()),
-        resident_update,
+        resident_update_and_proofread,
         resident_view,
     )
     .title(title_static)
     .subscription(resident_subscription)
-    .window_size(Size::new(500.0, 150.0))
+    .window_size(Size::new(500.0, 190.0))
     .window(window::Settings {
         icon,
         ..Default::default()
@@ -445,3 +2743,26 @@ pub fn run_gui() -> iced::Result {
     .default_font(Font::with_name("Noto Sans CJK JP"))
     .run()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_range() {
+        let mut content = text_editor::Content::with_text("今日は良い天気です");
+        let at = |column| text_editor::Position { line: 0, column };
+        select_range(&mut content, at(0), at(6));
+        assert_eq!(content.selection().as_deref(), Some("今日"));
+
+        // An empty range moves the cursor and drops the selection
+        select_range(&mut content, at(9), at(9));
+        assert_eq!(content.selection(), None);
+        assert_eq!(content.cursor().position, at(9));
+
+        // A new range replaces the old selection
+        select_range(&mut content, at(9), at(15));
+        select_range(&mut content, at(0), at(6));
+        assert_eq!(content.selection().as_deref(), Some("今日"));
+    }
+}
@@ -0,0 +1,190 @@
+use iced::keyboard::{self, Key};
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Id};
+use iced::widget::operation::focus;
+use iced::{event, Element, Event, Font, Length, Size, Subscription, Task};
+use iced::{Background, Border, Color, Theme};
+use iced::window;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+use crate::hook::{self, HookInput, PermissionDecision};
+
+/// Static ID for the reason input (focused on startup)
+static REASON_ID: LazyLock<Id> = LazyLock::new(Id::unique);
+
+/// State of the PreToolUse approval dialog
+pub struct Approval {
+    tool_name: String,
+    details: String,
+    reason: String,
+}
+
+/// Messages for the approval dialog
+#[derive(Debug, Clone)]
+pub enum ApprovalMessage {
+    ReasonChanged(String),
+    Decide(PermissionDecision),
+    Event(Event),
+}
+
+/// Human-readable summary of a tool call (the command for Bash, paths for file tools)
+pub fn describe_tool_input(tool_name: &str, input: &Value) -> String {
+    let field = |name: &str| input.get(name).and_then(Value::as_str);
+    match tool_name {
+        "Bash" => match (field("command"), field("description")) {
+            (Some(command), Some(description)) => format!("{}\n\n# {}", command, description),
+            (Some(command), None) => command.to_string(),
+            _ => serde_json::to_string_pretty(input).unwrap_or_default(),
+        },
+        "Edit" | "MultiEdit" | "Write" | "Read" | "NotebookEdit" => {
+            let path = field("file_path").or_else(|| field("notebook_path")).unwrap_or_default();
+            let mut lines = vec![path.to_string()];
+            if let Some(old) = field("old_string") {
+                lines.push(format!("\n- {}", old.replace('\n', "\n- ")));
+            }
+            if let Some(new) = field("new_string").or_else(|| field("content")) {
+                lines.push(format!("\n+ {}", new.replace('\n', "\n+ ")));
+            }
+            lines.join("\n")
+        }
+        "WebFetch" => field("url").unwrap_or_default().to_string(),
+        _ => serde_json::to_string_pretty(input).unwrap_or_default(),
+    }
+}
+
+fn update(state: &mut Approval, message: ApprovalMessage) -> Task<ApprovalMessage> {
+    match message {
+        ApprovalMessage::ReasonChanged(reason) => {
+            state.reason = reason;
+            Task::none()
+        }
+        ApprovalMessage::Decide(decision) => {
+            if let Err(e) = hook::write_permission_decision(decision, state.reason.trim()) {
+                eprintln!("Error writing hook output: {}", e);
+            }
+            window::latest().and_then(window::close)
+        }
+        ApprovalMessage::Event(event) => {
+            if let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event {
+                match key {
+                    // Ctrl+Enter approves, Ctrl+Shift+Enter denies
+                    Key::Named(keyboard::key::Named::Enter) if modifiers.control() => {
+                        let decision = if modifiers.shift() {
+                            PermissionDecision::Deny
+                        } else {
+                            PermissionDecision::Allow
+                        };
+                        return update(state, ApprovalMessage::Decide(decision));
+                    }
+                    // Esc leaves the decision to Claude Code's own prompt
+                    Key::Named(keyboard::key::Named::Escape) => {
+                        return window::latest().and_then(window::close);
+                    }
+                    _ => {}
+                }
+            }
+            Task::none()
+        }
+    }
+}
+
+fn view(state: &Approval) -> Element<'_, ApprovalMessage> {
+    let details = container(scrollable(
+        text(&state.details)
+            .size(13)
+            .font(Font::MONOSPACE)
+            .color(Color::from_rgb8(205, 214, 244)), // Text
+    ))
+    .padding(8)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .style(|_theme: &Theme| container::Style {
+        background: Some(Background::Color(Color::from_rgb8(49, 50, 68))), // Surface0
+        border: Border {
+            radius: 6.0.into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let reason = text_input("Reason (optional, sent to Claude on deny)", &state.reason)
+        .id(REASON_ID.clone())
+        .on_input(ApprovalMessage::ReasonChanged)
+        .padding(8);
+
+    let deny_button = button(text("Deny").size(14))
+        .padding([8, 16])
+        .style(button::danger)
+        .on_press(ApprovalMessage::Decide(PermissionDecision::Deny));
+
+    let approve_button = button(text("Approve").size(14))
+        .padding([8, 16])
+        .style(button::success)
+        .on_press(ApprovalMessage::Decide(PermissionDecision::Allow));
+
+    let content = column![
+        text(format!("Claude wants to use {}", state.tool_name)).size(18),
+        details,
+        reason,
+        row![
+            text("Ctrl+Enter: Approve | Ctrl+Shift+Enter: Deny | Esc: Ask in terminal")
+                .size(11)
+                .color(Color::from_rgb8(108, 112, 134)) // Overlay0
+                .width(Length::Fill),
+            deny_button,
+            approve_button,
+        ]
+        .spacing(10),
+    ]
+    .spacing(10)
+    .padding(12);
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(|_theme: &Theme| container::Style {
+            background: Some(Background::Color(Color::from_rgb8(30, 30, 46))), // Base
+            ..Default::default()
+        })
+        .into()
+}
+
+fn subscription(_state: &Approval) -> Subscription<ApprovalMessage> {
+    event::listen().map(ApprovalMessage::Event)
+}
+
+/// Show the approval dialog for a PreToolUse hook and print the decision to stdout
+pub fn run_approval_gui(input: &HookInput) -> iced::Result {
+    let tool_name = input.tool_name.clone();
+    let details = describe_tool_input(&input.tool_name, &input.tool_input);
+
+    let icon = window::icon::from_file_data(
+        include_bytes!("../assets/MojiBridge-Icon.png"),
+        None,
+    ).ok();
+
+    iced::application(
+        move || {
+            let state = Approval {
+                tool_name: tool_name.clone(),
+                details: details.clone(),
+                reason: String::new(),
+            };
+            (state, focus(REASON_ID.clone()))
+        },
+        update,
+        view,
+    )
+    .title("MojiBridge - Permission")
+    .subscription(subscription)
+    .window_size(Size::new(560.0, 360.0))
+    .window(window::Settings {
+        icon,
+        level: window::Level::AlwaysOnTop,
+        ..Default::default()
+    })
+    .theme(|_state: &Approval| Theme::Dark)
+    .font(include_bytes!("../assets/NotoSansJP-SemiBold.ttf").as_slice())
+    .default_font(Font::with_name("Noto Sans CJK JP"))
+    .run()
+}
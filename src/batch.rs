@@ -0,0 +1,236 @@
+use clap::Args;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::ipc;
+use crate::logger;
+
+/// How often to poll the resident instance while waiting for Claude
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Arguments for `moji-bridge batch`
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Prompt file: Markdown (prompts separated by `---` lines) or YAML (list of strings)
+    pub file: PathBuf,
+
+    /// Target instance (name, label or terminal hwnd); optional if only one is running
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Print the prompts without sending anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Resume from prompt N (1-based)
+    #[arg(long, default_value_t = 1)]
+    pub from: usize,
+
+    /// Maximum seconds to wait for Claude to finish each prompt
+    #[arg(long, default_value_t = 1800)]
+    pub timeout: u64,
+
+    /// Consider Claude finished once the transcript has been idle this many seconds
+    #[arg(long, default_value_t = 60)]
+    pub idle: u64,
+}
+
+/// YAML prompt files: either a plain list or a `prompts:` key
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum YamlPrompts {
+    List(Vec<String>),
+    Map { prompts: Vec<String> },
+}
+
+/// Split a prompt file into individual prompts (format chosen by extension)
+pub fn parse_prompts(path: &Path, content: &str) -> Result<Vec<String>, String> {
+    let is_yaml = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"));
+
+    let prompts = if is_yaml {
+        match serde_yaml::from_str::<YamlPrompts>(content)
+            .map_err(|e| format!("Failed to parse YAML: {}", e))?
+        {
+            YamlPrompts::List(prompts) | YamlPrompts::Map { prompts } => prompts,
+        }
+    } else {
+        split_markdown(content)
+    };
+
+    Ok(prompts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect())
+}
+
+/// Split Markdown on `---` lines, ignoring separators inside code fences
+fn split_markdown(content: &str) -> Vec<String> {
+    let mut prompts = Vec::new();
+    let mut current = String::new();
+    let mut in_fence = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && trimmed == "---" {
+            prompts.push(std::mem::take(&mut current));
+            continue;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    prompts.push(current);
+    prompts
+}
+
+/// How a prompt was detected as finished
+enum Completion {
+    Stop,
+    Idle,
+}
+
+fn query_status(instance: &ipc::InstanceInfo) -> Result<ipc::InstanceStatus, String> {
+    let response = ipc::send(instance, &ipc::Request::Status, Some(Duration::from_secs(5)))?;
+    response
+        .status
+        .ok_or_else(|| format!("Instance {} returned no status: {}", instance.name, response.message))
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Wait until a Stop hook arrives or the transcript goes quiet after activity
+fn wait_for_completion(
+    instance: &ipc::InstanceInfo,
+    before: &ipc::InstanceStatus,
+    timeout: Duration,
+    idle: Duration,
+) -> Result<Completion, String> {
+    let started = Instant::now();
+    let mut last_mtime = before.transcript_path.as_deref().and_then(modified);
+    let mut last_change = Instant::now();
+    let mut saw_activity = false;
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let status = query_status(instance)?;
+        if status.stop_count > before.stop_count {
+            return Ok(Completion::Stop);
+        }
+
+        let transcript = status.transcript_path.or_else(|| before.transcript_path.clone());
+        if let Some(path) = transcript {
+            let mtime = modified(&path);
+            if mtime != last_mtime {
+                last_mtime = mtime;
+                last_change = Instant::now();
+                saw_activity = true;
+            } else if saw_activity && last_change.elapsed() >= idle {
+                return Ok(Completion::Idle);
+            }
+        }
+
+        if started.elapsed() >= timeout {
+            return Err(format!("Timed out after {}s", timeout.as_secs()));
+        }
+    }
+}
+
+/// First line of a prompt, for progress output
+fn summary(prompt: &str) -> String {
+    let first_line = prompt.lines().next().unwrap_or_default();
+    let short: String = first_line.chars().take(60).collect();
+    if short.len() < prompt.len() {
+        format!("{}…", short)
+    } else {
+        short
+    }
+}
+
+/// Run `moji-bridge batch`
+pub fn run(args: &BatchArgs) -> Result<(), String> {
+    let content = std::fs::read_to_string(&args.file)
+        .map_err(|e| format!("Failed to read {}: {}", args.file.display(), e))?;
+    let prompts = parse_prompts(&args.file, &content)?;
+    let total = prompts.len();
+    if total == 0 {
+        return Err(format!("No prompts found in {}", args.file.display()));
+    }
+    if args.from == 0 || args.from > total {
+        return Err(format!("--from must be between 1 and {}", total));
+    }
+
+    if args.dry_run {
+        for (index, prompt) in prompts.iter().enumerate().skip(args.from - 1) {
+            println!("===== [{}/{}] =====", index + 1, total);
+            println!("{}", prompt);
+        }
+        return Ok(());
+    }
+
+    let instance = ipc::resolve_instance(args.to.as_deref())?;
+    println!("Sending {} prompt(s) to {}", total - (args.from - 1), instance.name);
+
+    for (index, prompt) in prompts.iter().enumerate().skip(args.from - 1) {
+        let step = index + 1;
+        println!("[{}/{}] {}", step, total, summary(prompt));
+        logger::log(&format!("[DEBUG batch] Sending prompt {}/{}", step, total));
+
+        let before = query_status(&instance)?;
+        let response = ipc::send(
+            &instance,
+            &ipc::Request::Deliver { text: prompt.clone(), deferred: false },
+            Some(Duration::from_secs(10)),
+        )?;
+        if !response.ok {
+            return Err(format!("Prompt {} failed: {} (resume with --from {})", step, response.message, step));
+        }
+
+        let completion = wait_for_completion(
+            &instance,
+            &before,
+            Duration::from_secs(args.timeout),
+            Duration::from_secs(args.idle),
+        )
+        .map_err(|e| format!("Prompt {}: {} (resume with --from {})", step, e, step + 1))?;
+
+        match completion {
+            Completion::Stop => println!("[{}/{}] done", step, total),
+            Completion::Idle => println!("[{}/{}] done (transcript idle)", step, total),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_markdown() {
+        let content = "最初のプロンプト\n---\n二つ目\n```\n---\n```\n---\n\n";
+        let prompts = parse_prompts(Path::new("prompts.md"), content).unwrap();
+        assert_eq!(prompts, ["最初のプロンプト", "二つ目\n```\n---\n```"]);
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let list = "- レビューしてください\n- |\n  テストを追加\n  してください\n";
+        let prompts = parse_prompts(Path::new("prompts.yaml"), list).unwrap();
+        assert_eq!(prompts, ["レビューしてください", "テストを追加\nしてください"]);
+
+        let map = "prompts:\n  - one\n  - two\n";
+        let prompts = parse_prompts(Path::new("prompts.yml"), map).unwrap();
+        assert_eq!(prompts, ["one", "two"]);
+    }
+}
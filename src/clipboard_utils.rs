@@ -1,3 +1,6 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use arboard::Clipboard;
 
 /// Write text to the system clipboard
@@ -21,3 +24,38 @@ pub fn read_from_clipboard() -> Result<String, String> {
         .get_text()
         .map_err(|e| format!("Failed to read from clipboard: {}", e))
 }
+
+/// Read HTML from the system clipboard (None when the clipboard holds no HTML)
+pub fn read_html_from_clipboard() -> Option<String> {
+    Clipboard::new().ok()?.get().html().ok()
+}
+
+/// Save the clipboard image as a PNG in `dir`; Ok(None) when the clipboard holds no image
+pub fn save_clipboard_image(dir: &Path) -> Result<Option<PathBuf>, String> {
+    let mut clipboard = Clipboard::new()
+        .map_err(|e| format!("Failed to access clipboard: {}", e))?;
+
+    let image = match clipboard.get_image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read image from clipboard: {}", e)),
+    };
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+    let path = dir.join(format!("paste-{}.png", millis));
+    write_png(&path, image.width as u32, image.height as u32, &image.bytes)?;
+    Ok(Some(path))
+}
+
+/// Encode RGBA pixels as a PNG file
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// Built-in Claude Code slash commands (name, description, argument hint)
+const BUILTIN_COMMANDS: &[(&str, &str, &str)] = &[
+    ("add-dir", "Add a new working directory", "<path>"),
+    ("agents", "Manage agent configurations", ""),
+    ("bug", "Report a bug to Anthropic", ""),
+    ("clear", "Clear conversation history", ""),
+    ("compact", "Compact the conversation, with optional focus instructions", "[instructions]"),
+    ("config", "Open the settings panel", ""),
+    ("context", "Show current context usage", ""),
+    ("cost", "Show token usage and cost", ""),
+    ("doctor", "Check the health of the installation", ""),
+    ("exit", "Exit Claude Code", ""),
+    ("export", "Export the conversation", "[filename]"),
+    ("help", "Show help", ""),
+    ("hooks", "Manage hook configurations", ""),
+    ("init", "Initialize the project with a CLAUDE.md guide", ""),
+    ("login", "Switch Anthropic accounts", ""),
+    ("logout", "Sign out", ""),
+    ("mcp", "Manage MCP server connections", ""),
+    ("memory", "Edit CLAUDE.md memory files", ""),
+    ("model", "Select or change the model", "[model]"),
+    ("output-style", "Set the output style", "[style]"),
+    ("permissions", "View or update permissions", ""),
+    ("pr-comments", "View pull request comments", ""),
+    ("resume", "Resume a conversation", ""),
+    ("review", "Request a code review", "[pr]"),
+    ("rewind", "Rewind the conversation and/or code", ""),
+    ("security-review", "Security review of pending changes", ""),
+    ("status", "Show version, model and account status", ""),
+    ("statusline", "Set up the status line", ""),
+    ("terminal-setup", "Install Shift+Enter key binding for newlines", ""),
+    ("todos", "List current todo items", ""),
+    ("usage", "Show plan usage limits", ""),
+    ("vim", "Toggle vim editing mode", ""),
+];
+
+/// Where a slash command comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandSource {
+    Builtin,
+    User,
+    Project,
+}
+
+/// A slash command offered by the autocomplete popup
+#[derive(Debug, Clone)]
+pub struct SlashCommand {
+    /// Name including the leading slash (namespaced as `/dir:name` for subdirectories)
+    pub name: String,
+    pub description: String,
+    pub argument_hint: Option<String>,
+    pub source: CommandSource,
+}
+
+/// Split a Markdown file into its YAML-ish frontmatter (`key: value` lines) and body
+pub fn parse_frontmatter(content: &str) -> (Vec<(String, String)>, &str) {
+    let Some(rest) = content.strip_prefix("---") else {
+        return (Vec::new(), content);
+    };
+    let rest = rest.trim_start_matches(['\r', '\n']);
+    let Some(end) = rest.find("\n---") else {
+        return (Vec::new(), content);
+    };
+
+    let fields = rest[..end]
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect();
+    let body = rest[end + 4..].trim_start_matches(|c| c != '\n').trim_start_matches(['\r', '\n']);
+    (fields, body)
+}
+
+/// Read one command file
+fn parse_command(name: String, content: &str, source: CommandSource) -> SlashCommand {
+    let (fields, body) = parse_frontmatter(content);
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+            .filter(|v| !v.is_empty())
+    };
+
+    // Without a description, use the first line of the prompt
+    let description = field("description").unwrap_or_else(|| {
+        body.lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or_default()
+            .chars()
+            .take(80)
+            .collect()
+    });
+    let argument_hint = field("argument-hint").or_else(|| {
+        (body.contains("$ARGUMENTS") || body.contains("$1")).then(|| "<arguments>".to_string())
+    });
+
+    SlashCommand { name, description, argument_hint, source }
+}
+
+/// Collect `*.md` commands under a directory (subdirectories become namespaces)
+fn scan_dir(dir: &Path, namespace: &str, source: CommandSource, commands: &mut Vec<SlashCommand>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if path.is_dir() {
+            let namespace = if namespace.is_empty() {
+                stem.to_string()
+            } else {
+                format!("{}:{}", namespace, stem)
+            };
+            scan_dir(&path, &namespace, source, commands);
+        } else if path.extension().is_some_and(|e| e == "md") {
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let name = if namespace.is_empty() {
+                format!("/{}", stem)
+            } else {
+                format!("/{}:{}", namespace, stem)
+            };
+            commands.push(parse_command(name, &content, source));
+        }
+    }
+}
+
+/// Directories holding custom commands: `~/.claude/commands` and `<cwd>/.claude/commands`
+fn command_dirs(cwd: Option<&str>) -> Vec<(PathBuf, CommandSource)> {
+    let mut dirs = Vec::new();
+    if let Some(home) = paths::home_dir() {
+        dirs.push((home.join(".claude").join("commands"), CommandSource::User));
+    }
+    if let Some(cwd) = cwd {
+        dirs.push((Path::new(cwd).join(".claude").join("commands"), CommandSource::Project));
+    }
+    dirs
+}
+
+/// All slash commands: custom ones first (project, then user), then built-ins
+pub fn load_commands(cwd: Option<&str>) -> Vec<SlashCommand> {
+    let mut custom = Vec::new();
+    for (dir, source) in command_dirs(cwd) {
+        scan_dir(&dir, "", source, &mut custom);
+    }
+    custom.sort_by(|a, b| {
+        (a.source != CommandSource::Project, &a.name).cmp(&(b.source != CommandSource::Project, &b.name))
+    });
+
+    let builtins = BUILTIN_COMMANDS.iter().map(|(name, description, hint)| SlashCommand {
+        name: format!("/{}", name),
+        description: description.to_string(),
+        argument_hint: (!hint.is_empty()).then(|| hint.to_string()),
+        source: CommandSource::Builtin,
+    });
+    custom.into_iter().chain(builtins).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let content = "---\ndescription: 差分をレビュー\nargument-hint: [branch]\n---\n$ARGUMENTS をレビューしてください\n";
+        let command = parse_command("/review-ja".to_string(), content, CommandSource::User);
+        assert_eq!(command.description, "差分をレビュー");
+        assert_eq!(command.argument_hint.as_deref(), Some("[branch]"));
+
+        let command = parse_command("/fix".to_string(), "Issue $ARGUMENTS を修正\n", CommandSource::Project);
+        assert_eq!(command.description, "Issue $ARGUMENTS を修正");
+        assert_eq!(command.argument_hint.as_deref(), Some("<arguments>"));
+    }
+}
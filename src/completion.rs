@@ -0,0 +1,98 @@
+/// Maximum number of entries shown in the completion popup
+pub const MAX_ITEMS: usize = 6;
+
+/// One entry of the completion popup
+#[derive(Debug, Clone)]
+pub struct CompletionItem {
+    pub label: String,
+    /// Secondary text (description, directory, ...)
+    pub detail: String,
+    /// Text that replaces the typed token
+    pub insert: String,
+}
+
+/// Open completion popup: candidates for the token just before the cursor
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub items: Vec<CompletionItem>,
+    pub selected: usize,
+    /// Length in bytes of the typed token that accepting replaces
+    pub replace_len: usize,
+    /// Enter and ↑/↓ go to the popup as well as Tab (off for completions that pop up during
+    /// normal typing, where they belong to the editor)
+    pub captures_keys: bool,
+}
+
+impl Completion {
+    /// Create a popup, or None if there is nothing to offer
+    pub fn new(items: Vec<CompletionItem>, replace_len: usize) -> Option<Self> {
+        (!items.is_empty()).then_some(Self {
+            items,
+            selected: 0,
+            replace_len,
+            captures_keys: true,
+        })
+    }
+
+    /// Leave Enter and ↑/↓ to the editor: only Tab (accepting the top match) goes to the popup
+    pub fn tab_only(self) -> Self {
+        Self {
+            captures_keys: false,
+            ..self
+        }
+    }
+
+    /// Move the selection, wrapping around at both ends
+    pub fn move_selection(&mut self, delta: isize) {
+        let len = self.items.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+}
+
+/// Fuzzy subsequence match (case-insensitive); higher is better, None if no match
+/// Rewards matches at the start, after separators and consecutive runs
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let index = (position..candidate_chars.len())
+            .find(|&i| candidate_chars[i].to_lowercase().eq(std::iter::once(q)))?;
+
+        score += 1;
+        if index == 0 {
+            score += 8;
+        } else if matches!(candidate_chars[index - 1], '/' | '\\' | '_' | '-' | '.' | ':' | ' ') {
+            score += 6;
+        }
+        if previous_match.is_some_and(|p| p + 1 == index) {
+            score += 4;
+        }
+        score -= (index - position) as i64 / 4;
+
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    // Prefer shorter candidates when scores tie
+    Some(score * 1000 - candidate_chars.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("cmp", "compact").is_some());
+        assert!(fuzzy_score("xyz", "compact").is_none());
+        // Prefix and word-start matches rank above scattered ones
+        assert!(fuzzy_score("rev", "review") > fuzzy_score("rev", "pr-review-comments"));
+        assert!(fuzzy_score("app", "src/app.rs") > fuzzy_score("app", "src/approval.rs"));
+        assert!(fuzzy_score("sr", "security-review") > fuzzy_score("sr", "status"));
+    }
+}
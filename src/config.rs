@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::lint::LintConfig;
+use crate::logger;
+use crate::normalize::Normalization;
+use crate::paste::PasteTransform;
+use crate::paths;
+use crate::punctuation::PunctuationProfile;
+use crate::spell::SpellcheckConfig;
+
+/// User settings, read from `<data dir>/config.json`
+/// Every field is optional; missing fields fall back to the defaults below
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Flash the taskbar entry when Claude sends a notification
+    pub request_attention: bool,
+    /// Buttons shown above the editor (an empty list hides the toolbar)
+    pub quick_actions: Vec<QuickAction>,
+    /// Abbreviations expanded when followed by a space or Tab (e.g. `;rv` -> a review request)
+    pub abbreviations: BTreeMap<String, String>,
+    /// Cleanups applied to pasted multi-line text (stack traces, terminal output)
+    pub paste_transforms: Vec<PasteTransform>,
+    /// Show a preview with per-transform toggles before pasting cleaned-up text
+    pub paste_preview: bool,
+    /// Cleanups applied to the prompt when it is sent (full-width letters, half-width kana, ...)
+    pub normalization: Normalization,
+    /// Punctuation conventions (、。 vs ，．, quotes, parentheses) applied with Ctrl+P or on send
+    pub punctuation: PunctuationProfile,
+    /// Japanese proofreading rules shown beside the editor
+    pub lint: LintConfig,
+    /// English spellchecking with a local Hunspell dictionary
+    pub spellcheck: SpellcheckConfig,
+    /// Kanji for the built-in romaji input: hiragana reading -> words offered on Space
+    pub kana_dictionary: BTreeMap<String, Vec<String>>,
+}
+
+/// Per-project settings, read from `<cwd>/.moji-bridge/config.json`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProjectConfig {
+    /// Added to (and overriding) the global abbreviations; an empty value removes one
+    abbreviations: BTreeMap<String, String>,
+    /// Replaces the global punctuation profile
+    punctuation: Option<PunctuationProfile>,
+    /// Replaces the global proofreading settings
+    lint: Option<LintConfig>,
+}
+
+/// A toolbar button that sends a fixed text (typically a slash command)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickAction {
+    pub label: String,
+    pub text: String,
+    /// Ask before sending
+    #[serde(default)]
+    pub confirm: bool,
+    /// Append the current editor text as the argument (e.g. `/compact <focus>`)
+    #[serde(default)]
+    pub argument: bool,
+}
+
+impl QuickAction {
+    fn new(text: &str, confirm: bool, argument: bool) -> Self {
+        Self {
+            label: text.to_string(),
+            text: text.to_string(),
+            confirm,
+            argument,
+        }
+    }
+
+    /// Text to send, with the editor text appended when the action takes an argument
+    pub fn command(&self, editor_text: &str) -> String {
+        if self.argument && !editor_text.is_empty() {
+            format!("{} {}", self.text, editor_text)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            request_attention: true,
+            quick_actions: vec![
+                QuickAction::new("/clear", true, false),
+                QuickAction::new("/compact", false, true),
+                QuickAction::new("/review", false, true),
+            ],
+            abbreviations: BTreeMap::new(),
+            paste_transforms: PasteTransform::ALL.to_vec(),
+            paste_preview: true,
+            normalization: Normalization::default(),
+            punctuation: PunctuationProfile::default(),
+            lint: LintConfig::default(),
+            spellcheck: SpellcheckConfig::default(),
+            kana_dictionary: BTreeMap::new(),
+        }
+    }
+}
+
+/// Path of the global config file
+pub fn config_path() -> PathBuf {
+    paths::data_dir().join("config.json")
+}
+
+/// Read a JSON settings file, falling back to defaults if it is missing or invalid
+fn read_json<T: Default + for<'de> Deserialize<'de>>(path: &Path) -> T {
+    let Ok(json) = std::fs::read_to_string(path) else {
+        return T::default();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        logger::log(&format!("[DEBUG config] Invalid {}: {}", path.display(), e));
+        T::default()
+    })
+}
+
+/// Load the config, falling back to defaults if the file is missing or invalid
+pub fn load() -> Config {
+    read_json(&config_path())
+}
+
+/// Load the global config with the project's overrides applied
+pub fn load_for_project(cwd: Option<&str>) -> Config {
+    let mut config = load();
+    if let Some(cwd) = cwd {
+        let project: ProjectConfig = read_json(&paths::project_dir(cwd).join("config.json"));
+        config.abbreviations.extend(project.abbreviations);
+        config.abbreviations.retain(|_, expansion| !expansion.is_empty());
+        if let Some(punctuation) = project.punctuation {
+            config.punctuation = punctuation;
+        }
+        if let Some(lint) = project.lint {
+            config.lint = lint;
+        }
+    }
+    config
+}
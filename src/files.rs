@@ -0,0 +1,172 @@
+use std::path::Path;
+
+use crate::logger;
+
+/// Upper bound on files listed for the `@` picker (keeps huge trees responsive)
+const MAX_FILES: usize = 20_000;
+
+/// Files above this size get a warning before an excerpt is inserted
+pub const LARGE_FILE_BYTES: u64 = 256 * 1024;
+
+/// Files above this size are not read at all
+const MAX_READ_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Lines preselected when excerpting a large file
+pub const LARGE_FILE_DEFAULT_LINES: usize = 200;
+
+/// Files under `root` as `/`-separated relative paths, honouring `.gitignore`
+/// Hidden files and directories are skipped, like Claude Code's own picker
+pub fn list_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let walker = ignore::WalkBuilder::new(root).require_git(false).build();
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                logger::log(&format!("[DEBUG files] {}", e));
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let path: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        files.push(path.join("/"));
+        if files.len() >= MAX_FILES {
+            logger::log(&format!("[DEBUG files] Stopped listing at {} files", MAX_FILES));
+            break;
+        }
+    }
+    files
+}
+
+/// The `@mention` being typed at the end of `prefix` (text before the cursor), without the `@`
+/// The `@` must follow whitespace or Japanese text (which has no spaces), so e-mail addresses
+/// don't open the picker
+pub fn mention_query(prefix: &str) -> Option<&str> {
+    let start = prefix.rfind('@')?;
+    let query = &prefix[start + 1..];
+    let starts_word = prefix[..start]
+        .chars()
+        .next_back()
+        .is_none_or(|c| c.is_whitespace() || !c.is_ascii());
+    (starts_word && !query.contains(char::is_whitespace)).then_some(query)
+}
+
+/// A file read for inserting an excerpt
+#[derive(Debug)]
+pub struct FileContent {
+    pub text: String,
+    pub line_count: usize,
+    pub size: u64,
+    /// Contains NUL bytes or isn't valid UTF-8 (text is empty then)
+    pub binary: bool,
+}
+
+/// Read a file for an excerpt, detecting binary content
+pub fn read_file_content(path: &Path) -> Result<FileContent, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_READ_BYTES {
+        return Err(format!("{} is too large ({} MB)", path.display(), size / (1024 * 1024)));
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let text = if bytes.iter().take(8000).any(|&b| b == 0) {
+        None
+    } else {
+        String::from_utf8(bytes).ok()
+    };
+    Ok(match text {
+        Some(text) => FileContent {
+            line_count: text.lines().count(),
+            text: text.replace("\r\n", "\n"),
+            size,
+            binary: false,
+        },
+        None => FileContent {
+            text: String::new(),
+            line_count: 0,
+            size,
+            binary: true,
+        },
+    })
+}
+
+/// Lines `start..=end` (1-based, clamped to the text)
+pub fn line_range(text: &str, start: usize, end: usize) -> String {
+    let start = start.max(1);
+    text.lines()
+        .skip(start - 1)
+        .take(end.saturating_sub(start) + 1)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Fence language tag for a file, from its extension
+pub fn language_for_path(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" => "powershell",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "sql" => "sql",
+        "md" => "markdown",
+        _ => "",
+    }
+}
+
+/// Markdown fenced code block; the fence grows if the text itself contains backtick fences
+pub fn fenced_block(language: &str, text: &str) -> String {
+    let mut fence = "```".to_string();
+    while text.contains(fence.as_str()) {
+        fence.push('`');
+    }
+    format!("{}{}\n{}\n{}\n", fence, language, text.trim_end_matches('\n'), fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mention_query() {
+        assert_eq!(mention_query("@"), Some(""));
+        assert_eq!(mention_query("この @src/ap"), Some("src/ap"));
+        assert_eq!(mention_query("このファイル@src/ap"), Some("src/ap"));
+        assert_eq!(mention_query("mail me@example.com"), None);
+        assert_eq!(mention_query("@src/app.rs を見て"), None);
+    }
+
+    #[test]
+    fn test_line_range() {
+        let text = "one\ntwo\nthree\nfour";
+        assert_eq!(line_range(text, 2, 3), "two\nthree");
+        assert_eq!(line_range(text, 3, 99), "three\nfour");
+        assert_eq!(language_for_path(Path::new("src/app.RS")), "rust");
+    }
+}
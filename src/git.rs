@@ -0,0 +1,195 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+/// Options for every invocation: paths as UTF-8 instead of octal escapes (`"\346\227\245.md"`),
+/// and no color codes even with `color.ui=always`, since the output goes into prompts
+const GIT_OPTIONS: &[&str] = &[
+    "-c", "core.quotepath=off",
+    "-c", "color.ui=never",
+    "-c", "color.diff=never",
+    "-c", "color.status=never",
+];
+
+/// Run git in `cwd` and return its stdout
+pub fn run_git(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    let mut cmd = Command::new("git");
+    cmd.args(GIT_OPTIONS).args(args).current_dir(cwd).stdin(Stdio::null());
+
+    // Don't flash a console window from the GUI process
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = cmd.output().map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Name of the checked-out branch (None outside a repository or on a detached HEAD)
+pub fn current_branch(cwd: &Path) -> Option<String> {
+    let branch = run_git(cwd, &["rev-parse", "--abbrev-ref", "HEAD"]).ok()?;
+    let branch = branch.trim();
+    (!branch.is_empty() && branch != "HEAD").then(|| branch.to_string())
+}
+
+/// Upper bound on git output inserted into the editor
+pub const MAX_CONTEXT_BYTES: usize = 16 * 1024;
+
+/// What the "Insert git context" panel can insert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitContext {
+    Branch,
+    Status,
+    StagedDiff,
+    Hunks,
+}
+
+impl GitContext {
+    pub const ALL: [GitContext; 4] = [Self::Branch, Self::Status, Self::StagedDiff, Self::Hunks];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Branch => "Branch",
+            Self::Status => "Status",
+            Self::StagedDiff => "Staged diff",
+            Self::Hunks => "Pick hunks…",
+        }
+    }
+}
+
+/// One `@@` hunk of a diff, with the file it belongs to
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub file: String,
+    /// The `@@ -a,b +c,d @@ ...` line
+    pub header: String,
+    /// Header and body lines
+    pub text: String,
+}
+
+/// `git status --short`
+pub fn status_short(cwd: &Path) -> Result<String, String> {
+    run_git(cwd, &["status", "--short"])
+}
+
+/// `git diff --cached`
+pub fn staged_diff(cwd: &Path) -> Result<String, String> {
+    run_git(cwd, &["diff", "--cached"])
+}
+
+/// Hunks of all uncommitted changes (staged and unstaged)
+pub fn diff_hunks(cwd: &Path) -> Result<Vec<Hunk>, String> {
+    // A repository without commits has no HEAD to diff against
+    let diff = run_git(cwd, &["diff", "HEAD"]).or_else(|_| run_git(cwd, &["diff"]))?;
+    Ok(parse_hunks(&diff))
+}
+
+/// Split unified diff output into hunks
+pub fn parse_hunks(diff: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut file = String::new();
+    let mut in_hunk = false;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_hunk = false;
+            // `diff --git a/path b/path`; refined by the `+++` line below
+            file = line.rsplit(" b/").next().unwrap_or_default().to_string();
+        } else if let Some(path) = line.strip_prefix("+++ b/").filter(|_| !in_hunk) {
+            file = path.to_string();
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+            hunks.push(Hunk {
+                file: file.clone(),
+                header: line.to_string(),
+                text: format!("{}\n", line),
+            });
+        } else if in_hunk {
+            if let Some(hunk) = hunks.last_mut() {
+                hunk.text.push_str(line);
+                hunk.text.push('\n');
+            }
+        }
+    }
+    hunks
+}
+
+/// Reassemble chosen hunks into a diff, with a file header before each file's hunks
+pub fn hunks_to_diff(hunks: &[&Hunk]) -> String {
+    let mut diff = String::new();
+    let mut current_file: Option<&str> = None;
+    for hunk in hunks {
+        if current_file != Some(hunk.file.as_str()) {
+            diff.push_str(&format!("--- a/{0}\n+++ b/{0}\n", hunk.file));
+            current_file = Some(&hunk.file);
+        }
+        diff.push_str(&hunk.text);
+    }
+    diff
+}
+
+/// Cut `text` to at most `max` bytes on a line boundary; returns the text and whether it was cut
+pub fn truncate(text: &str, max: usize) -> (String, bool) {
+    if text.len() <= max {
+        return (text.to_string(), false);
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = text[..end].rfind('\n').map_or(end, |newline| newline + 1);
+    let omitted = text[end..].lines().count();
+    (format!("{}... ({} more lines)\n", &text[..end], omitted), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hunks() {
+        let diff = "diff --git a/src/app.rs b/src/app.rs\n\
+                    index 1111111..2222222 100644\n\
+                    --- a/src/app.rs\n\
+                    +++ b/src/app.rs\n\
+                    @@ -1,2 +1,2 @@\n\
+                    -old\n\
+                    +new\n\
+                    @@ -10 +10 @@ fn main()\n\
+                    +added\n\
+                    diff --git a/README.md b/README.md\n\
+                    --- a/README.md\n\
+                    +++ b/README.md\n\
+                    @@ -5 +5 @@\n\
+                    -a\n";
+        let hunks = parse_hunks(diff);
+        assert_eq!(hunks.len(), 3);
+        assert_eq!(hunks[1].file, "src/app.rs");
+        assert_eq!(hunks[1].header, "@@ -10 +10 @@ fn main()");
+        assert_eq!(hunks[2].file, "README.md");
+
+        // Non-ASCII paths come through unescaped with core.quotepath=off
+        let diff = "diff --git a/docs/設計メモ.md b/docs/設計メモ.md\n\
+                    --- a/docs/設計メモ.md\n\
+                    +++ b/docs/設計メモ.md\n\
+                    @@ -1 +1 @@\n\
+                    -旧\n\
+                    +新\n";
+        let japanese = parse_hunks(diff);
+        assert_eq!(japanese.len(), 1);
+        assert_eq!(japanese[0].file, "docs/設計メモ.md");
+        assert_eq!(japanese[0].text, "@@ -1 +1 @@\n-旧\n+新\n");
+
+        let chosen: Vec<&Hunk> = vec![&hunks[0], &hunks[2]];
+        let rebuilt = hunks_to_diff(&chosen);
+        assert!(rebuilt.starts_with("--- a/src/app.rs\n+++ b/src/app.rs\n@@ -1,2 +1,2 @@\n-old\n+new\n--- a/README.md"));
+    }
+}
@@ -2,12 +2,16 @@ use serde::{Deserialize, Serialize};
 use std::io::{self, Read, Write};
 
 use crate::clipboard_utils;
+use crate::ipc;
 use crate::logger;
 
 /// Maximum input size to prevent DoS attacks (100KB)
 const MAX_INPUT_SIZE: usize = 100 * 1024;
 
-/// Input from Claude Code's UserPromptSubmit hook
+/// PreToolUse carries the whole tool input (a large Write or Edit), so it may be bigger
+const MAX_TOOL_INPUT_SIZE: usize = 16 * 1024 * 1024;
+
+/// Input from Claude Code's hooks (UserPromptSubmit, SessionStart, Stop, ...)
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 pub struct HookInput {
@@ -19,17 +23,25 @@ pub struct HookInput {
     pub user_prompt: String,
     #[serde(default)]
     pub permission_mode: String,
+    #[serde(default)]
+    pub cwd: String,
+    #[serde(default)]
+    pub transcript_path: String,
+    #[serde(default)]
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: serde_json::Value,
+    #[serde(default)]
+    pub message: String,
 }
 
-/// Output to Claude Code's hook system (kept for future use and tests)
-#[allow(dead_code)]
+/// Output to Claude Code's hook system (UserPromptSubmit with additional context)
 #[derive(Debug, Serialize)]
 pub struct HookOutput {
     #[serde(rename = "hookSpecificOutput")]
     pub hook_specific_output: HookSpecificOutput,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 pub struct HookSpecificOutput {
     #[serde(rename = "hookEventName")]
@@ -38,6 +50,38 @@ pub struct HookSpecificOutput {
     pub additional_context: String,
 }
 
+/// Output that stops a UserPromptSubmit prompt from being sent (the reason is shown to the user)
+#[derive(Debug, Serialize)]
+pub struct BlockOutput {
+    pub decision: String,
+    pub reason: String,
+}
+
+/// Decision returned from a PreToolUse hook
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+}
+
+/// Output of a PreToolUse hook
+#[derive(Debug, Serialize)]
+pub struct PreToolUseOutput {
+    #[serde(rename = "hookSpecificOutput")]
+    pub hook_specific_output: PreToolUseSpecificOutput,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreToolUseSpecificOutput {
+    #[serde(rename = "hookEventName")]
+    pub hook_event_name: String,
+    #[serde(rename = "permissionDecision")]
+    pub permission_decision: PermissionDecision,
+    #[serde(rename = "permissionDecisionReason", skip_serializing_if = "String::is_empty")]
+    pub permission_decision_reason: String,
+}
+
 /// Read hook input from stdin (reads single line of JSON with size limit)
 pub fn read_hook_input() -> Result<HookInput, String> {
     let stdin = io::stdin();
@@ -45,12 +89,12 @@ pub fn read_hook_input() -> Result<HookInput, String> {
 
     // Read with size limit to prevent DoS attacks
     let bytes_read = stdin.lock()
-        .take(MAX_INPUT_SIZE as u64)
+        .take(MAX_TOOL_INPUT_SIZE as u64)
         .read_to_string(&mut input)
         .map_err(|e| format!("Failed to read stdin: {}", e))?;
 
-    if bytes_read >= MAX_INPUT_SIZE {
-        return Err(format!("Input too large (max {} bytes)", MAX_INPUT_SIZE));
+    if bytes_read >= MAX_TOOL_INPUT_SIZE {
+        return Err(format!("Input too large (max {} bytes)", MAX_TOOL_INPUT_SIZE));
     }
 
     logger::log(&format!("[DEBUG hook] Raw stdin input: {} bytes", input.len()));
@@ -59,7 +103,11 @@ pub fn read_hook_input() -> Result<HookInput, String> {
         return Err("No input received from stdin".to_string());
     }
 
-    serde_json::from_str(&input).map_err(|e| format!("Failed to parse JSON: {}", e))
+    let parsed: HookInput = serde_json::from_str(&input).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    if parsed.hook_event_name != "PreToolUse" && bytes_read >= MAX_INPUT_SIZE {
+        return Err(format!("Input too large (max {} bytes)", MAX_INPUT_SIZE));
+    }
+    Ok(parsed)
 }
 
 /// Write hook output to stdout
@@ -76,11 +124,104 @@ pub fn write_hook_output(text: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Write an expanded `//name` template to stdout as the prompt's additional context
+/// Fallback for sessions without a resident instance (see `redeliver_template`)
+pub fn write_template_output(name: &str, text: &str) -> Result<(), String> {
+    let output = HookOutput {
+        hook_specific_output: HookSpecificOutput {
+            hook_event_name: "UserPromptSubmit".to_string(),
+            additional_context: format!(
+                "[User's actual request, expanded from the template //{} (ignore the //{} line itself)]:\n{}",
+                name, name, text
+            ),
+        },
+    };
+    let json = serde_json::to_string(&output).map_err(|e| format!("Failed to serialize hook output: {}", e))?;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "{}", json).map_err(|e| format!("Failed to write to stdout: {}", e))
+}
+
+/// Block the prompt with a message for the user
+pub fn write_block_output(reason: &str) -> Result<(), String> {
+    let output = BlockOutput {
+        decision: "block".to_string(),
+        reason: reason.to_string(),
+    };
+    let json = serde_json::to_string(&output).map_err(|e| format!("Failed to serialize hook output: {}", e))?;
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "{}", json).map_err(|e| format!("Failed to write to stdout: {}", e))
+}
+
+/// Write a PreToolUse permission decision to stdout as JSON
+pub fn write_permission_decision(decision: PermissionDecision, reason: &str) -> Result<(), String> {
+    let output = PreToolUseOutput {
+        hook_specific_output: PreToolUseSpecificOutput {
+            hook_event_name: "PreToolUse".to_string(),
+            permission_decision: decision,
+            permission_decision_reason: reason.to_string(),
+        },
+    };
+    let json = serde_json::to_string(&output).map_err(|e| format!("Failed to serialize decision: {}", e))?;
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "{}", json).map_err(|e| format!("Failed to write to stdout: {}", e))
+}
+
 /// Check if the user prompt is a trigger for the input helper
 pub fn is_trigger(prompt: &str) -> bool {
     prompt.trim().starts_with("//")
 }
 
+/// Template invocation typed in Claude's input: `//name args` -> (name, args)
+/// A bare `//` or `// text` is not an invocation (it opens the input helper)
+pub fn template_invocation(prompt: &str) -> Option<(&str, &str)> {
+    let rest = prompt.trim().strip_prefix("//")?;
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let name = &rest[..end];
+    (!name.is_empty()).then(|| (name, rest[end..].trim()))
+}
+
+/// Have the session's resident instance send the expanded template as a new prompt once the hook
+/// has exited (the `//name` prompt itself is blocked)
+pub fn redeliver_template(input: &HookInput, text: &str) -> Result<(), String> {
+    let instance = ipc::find_instance_for_session(&input.session_id)
+        .ok_or_else(|| format!("No resident instance for session {}", input.session_id))?;
+    let request = ipc::Request::Deliver { text: text.to_string(), deferred: true };
+    let response = ipc::send(&instance, &request, Some(std::time::Duration::from_secs(5)))?;
+    if !response.ok {
+        return Err(response.message);
+    }
+    logger::log(&format!("[DEBUG hook] Template sent to {}: {}", instance.name, response.message));
+    Ok(())
+}
+
+/// Forward a Stop or Notification hook to the resident instance that owns the session
+/// On Stop the instance sends the next queued prompt, if any
+pub fn forward_to_resident(input: &HookInput) -> Result<(), String> {
+    let request = match input.hook_event_name.as_str() {
+        "Stop" => ipc::Request::Stop {
+            session_id: input.session_id.clone(),
+            transcript_path: input.transcript_path.clone(),
+        },
+        "Notification" => ipc::Request::Notify {
+            session_id: input.session_id.clone(),
+            message: input.message.clone(),
+        },
+        other => return Err(format!("Hook {} is not forwarded", other)),
+    };
+    let instance = ipc::find_instance_for_session(&input.session_id)
+        .ok_or_else(|| format!("No resident instance for session {}", input.session_id))?;
+    let response = ipc::send(&instance, &request, Some(std::time::Duration::from_secs(5)))?;
+    logger::log(&format!(
+        "[DEBUG hook] {} forwarded to {}: {}",
+        input.hook_event_name, instance.name, response.message
+    ));
+    Ok(())
+}
+
 /// Write hook output with content from clipboard
 /// This is used when the resident GUI has written input to clipboard
 #[allow(dead_code)]
@@ -108,6 +249,15 @@ mod tests {
         assert!(!is_trigger(""));
     }
 
+    #[test]
+    fn test_template_invocation() {
+        assert_eq!(template_invocation("//bug ログインできない"), Some(("bug", "ログインできない")));
+        assert_eq!(template_invocation("  //review"), Some(("review", "")));
+        assert_eq!(template_invocation("//"), None);
+        assert_eq!(template_invocation("// some text"), None);
+        assert_eq!(template_invocation("/review"), None);
+    }
+
     #[test]
     fn test_hook_output_serialization() {
         let output = HookOutput {
@@ -122,4 +272,19 @@ mod tests {
         assert!(json.contains("hookEventName"));
         assert!(json.contains("additionalContext"));
     }
+
+    #[test]
+    fn test_permission_decision_serialization() {
+        let output = PreToolUseOutput {
+            hook_specific_output: PreToolUseSpecificOutput {
+                hook_event_name: "PreToolUse".to_string(),
+                permission_decision: PermissionDecision::Deny,
+                permission_decision_reason: "本番DBには触らないでください".to_string(),
+            },
+        };
+
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains(r#""permissionDecision":"deny""#));
+        assert!(json.contains("permissionDecisionReason"));
+    }
 }
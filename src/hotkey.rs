@@ -41,6 +41,20 @@ pub fn set_own_moji_hwnd(hwnd: isize) {
     logger::log(&format!("[DEBUG hotkey] Own MojiBridge hwnd set to: {}", hwnd));
 }
 
+/// Bring the own MojiBridge window back to the foreground (after sending keys to the terminal)
+#[cfg(windows)]
+pub fn focus_own_window() {
+    let own_moji_hwnd = OWN_MOJI_HWND.load(Ordering::SeqCst);
+    if own_moji_hwnd != 0 {
+        focus_window(own_moji_hwnd);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn focus_own_window() {
+    // Not implemented for non-Windows
+}
+
 /// Get the terminal hwnd
 #[allow(dead_code)]
 pub fn get_terminal_hwnd() -> isize {
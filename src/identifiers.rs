@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::files;
+use crate::logger;
+
+/// How often the indexer polls for changed files
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Files larger than this are skipped (generated code, bundles, ...)
+const MAX_FILE_SIZE: u64 = 512 * 1024;
+
+/// Identifiers shorter than this aren't worth completing
+const MIN_IDENTIFIER_LEN: usize = 3;
+
+/// Characters typed before the popup opens
+const MIN_QUERY_LEN: usize = 2;
+
+/// Identifier -> number of occurrences across the project
+pub type IdentifierCounts = HashMap<String, u32>;
+
+/// Comment and string syntax of a source language family
+struct Syntax {
+    line_comment: &'static str,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// `'x'` is a character literal (Rust), not the start of a string
+    char_literals: bool,
+    keywords: &'static [&'static str],
+}
+
+const RUST: Syntax = Syntax {
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    // `'` also starts lifetimes, so only double quotes delimit strings
+    quotes: &['"'],
+    char_literals: true,
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "for", "impl", "let", "loop", "match", "mod", "move", "mut", "pub",
+        "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+        "unsafe", "use", "where", "while",
+    ],
+};
+
+const C_LIKE: Syntax = Syntax {
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    char_literals: false,
+    keywords: &[
+        "break", "case", "catch", "class", "const", "continue", "default", "delete", "else",
+        "enum", "export", "extends", "false", "final", "finally", "for", "func", "function",
+        "import", "interface", "let", "new", "null", "package", "private", "protected",
+        "public", "return", "static", "struct", "switch", "this", "throw", "true", "try",
+        "typeof", "var", "void", "while",
+    ],
+};
+
+const HASH_COMMENT: Syntax = Syntax {
+    line_comment: "#",
+    block_comment: None,
+    quotes: &['"', '\''],
+    char_literals: false,
+    keywords: &[
+        "and", "begin", "class", "def", "del", "elif", "else", "end", "except", "False",
+        "finally", "for", "from", "import", "lambda", "None", "not", "pass", "raise", "return",
+        "self", "then", "True", "while", "with", "yield",
+    ],
+};
+
+/// Syntax for a file, by extension (None for files we don't index)
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => Some(&RUST),
+        "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "java" | "kt" | "kts" | "go" | "swift" | "js"
+        | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "scala" | "dart" | "php" => Some(&C_LIKE),
+        "py" | "rb" | "sh" | "bash" | "zsh" | "ex" | "exs" | "pl" | "r" => Some(&HASH_COMMENT),
+        _ => None,
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Length of the character literal at the start of `rest` (`'x'`, `'\''`, `'\u{1F600}'`),
+/// or None for a lifetime (`'a`)
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        // Escapes: the closing quote comes after the escaped character
+        let (_, escaped) = chars.next()?;
+        let after = 2 + escaped.len_utf8();
+        return rest[after..].find('\'').map(|end| after + end + 1);
+    }
+    let (index, next) = chars.next()?;
+    (next == '\'' && first != '\'').then_some(index + 1)
+}
+
+/// Count the identifiers in source code, skipping comments, string literals and keywords
+fn tokenize(syntax: &Syntax, source: &str) -> IdentifierCounts {
+    let mut counts = IdentifierCounts::new();
+    let mut rest = source;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(syntax.line_comment) {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if let Some((open, close)) = syntax.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+            rest = rest[open.len()..].find(close).map_or("", |end| &rest[open.len() + end + close.len()..]);
+        } else if c == '\'' && syntax.char_literals {
+            // A character literal is skipped whole (`'"'` must not open a string); a lifetime
+            // only loses its quote
+            rest = &rest[char_literal_len(rest).unwrap_or(1)..];
+        } else if syntax.quotes.contains(&c) {
+            // Skip to the closing quote, honouring backslash escapes
+            let mut escaped = false;
+            let end = rest[1..].find(|ch: char| {
+                let closes = ch == c && !escaped;
+                escaped = ch == '\\' && !escaped;
+                closes
+            });
+            rest = end.map_or("", |end| &rest[end + 2..]);
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest.find(|ch: char| !is_identifier_char(ch)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            if word.len() >= MIN_IDENTIFIER_LEN && !syntax.keywords.contains(&word) {
+                *counts.entry(word.to_string()).or_default() += 1;
+            }
+            rest = &rest[end..];
+        } else if c.is_ascii_digit() {
+            // Numeric literals such as 0x1f or 10usize are not identifiers
+            let end = rest.find(|ch: char| !is_identifier_char(ch)).unwrap_or(rest.len());
+            rest = &rest[end..];
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    counts
+}
+
+/// The identifier being typed at the end of `prefix`: at least `MIN_QUERY_LEN` ASCII word
+/// characters starting with a letter, not glued to a preceding ASCII word or marker (so `@path`,
+/// `;abbreviation` etc. don't match)
+pub fn identifier_query(prefix: &str) -> Option<&str> {
+    let start = prefix
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_identifier_char(*c))
+        .last()
+        .map(|(i, _)| i)?;
+    let query = &prefix[start..];
+    let preceded_ok = !prefix[..start].ends_with(['@', '/', '.', '-', '$', ';']);
+    let starts_with_letter = query.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+    (query.len() >= MIN_QUERY_LEN && starts_with_letter && preceded_ok).then_some(query)
+}
+
+/// Identifiers starting with `query` (case-insensitive), most frequent first
+pub fn complete<'a>(counts: &'a IdentifierCounts, query: &str, limit: usize) -> Vec<(&'a str, u32)> {
+    let query = query.to_ascii_lowercase();
+    let mut matches: Vec<(&str, u32)> = counts
+        .iter()
+        .filter(|(word, _)| word.len() > query.len() && word.to_ascii_lowercase().starts_with(&query))
+        .map(|(word, count)| (word.as_str(), *count))
+        .collect();
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    matches.truncate(limit);
+    matches
+}
+
+/// Per-file state kept by the indexer thread
+struct IndexedFile {
+    modified: SystemTime,
+    counts: IdentifierCounts,
+}
+
+fn add_counts(totals: &mut IdentifierCounts, counts: &IdentifierCounts) {
+    for (word, count) in counts {
+        *totals.entry(word.clone()).or_default() += count;
+    }
+}
+
+fn subtract_counts(totals: &mut IdentifierCounts, counts: &IdentifierCounts) {
+    for (word, count) in counts {
+        if let Some(total) = totals.get_mut(word) {
+            *total = total.saturating_sub(*count);
+            if *total == 0 {
+                totals.remove(word);
+            }
+        }
+    }
+}
+
+/// Re-tokenize new and modified files and drop deleted ones; returns the number of changed files
+fn refresh(root: &Path, indexed: &mut HashMap<PathBuf, IndexedFile>, totals: &RwLock<IdentifierCounts>) -> usize {
+    let mut seen = HashSet::new();
+    let mut updates = Vec::new();
+
+    for relative in files::list_files(root) {
+        let path = root.join(&relative);
+        let Some(syntax) = syntax_for(&path) else {
+            continue;
+        };
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.len() > MAX_FILE_SIZE {
+            continue;
+        }
+        let Ok(modified) = metadata.modified() else {
+            continue;
+        };
+        seen.insert(path.clone());
+        if indexed.get(&path).is_some_and(|f| f.modified == modified) {
+            continue;
+        }
+        // Unreadable or non-UTF-8 files are indexed as empty so they aren't retried every pass
+        let source = std::fs::read_to_string(&path).unwrap_or_default();
+        updates.push((path, IndexedFile { modified, counts: tokenize(syntax, &source) }));
+    }
+
+    let removed: Vec<PathBuf> = indexed.keys().filter(|p| !seen.contains(*p)).cloned().collect();
+    let changed = updates.len() + removed.len();
+    if changed == 0 {
+        return 0;
+    }
+
+    let mut totals = totals.write().unwrap_or_else(|e| e.into_inner());
+    for path in removed {
+        if let Some(old) = indexed.remove(&path) {
+            subtract_counts(&mut totals, &old.counts);
+        }
+    }
+    for (path, file) in updates {
+        add_counts(&mut totals, &file.counts);
+        if let Some(old) = indexed.insert(path, file) {
+            subtract_counts(&mut totals, &old.counts);
+        }
+    }
+    changed
+}
+
+/// Index identifiers under `root` on a background thread
+/// This polls rather than watching: every `REFRESH_INTERVAL` it relists the tree and stats each
+/// file, and only re-tokenizes the ones whose modification time changed
+pub fn spawn_indexer(root: PathBuf) -> Arc<RwLock<IdentifierCounts>> {
+    let totals = Arc::new(RwLock::new(IdentifierCounts::new()));
+    let shared = Arc::clone(&totals);
+
+    std::thread::spawn(move || {
+        let mut indexed = HashMap::new();
+        loop {
+            let changed = refresh(&root, &mut indexed, &shared);
+            if changed > 0 {
+                let count = shared.read().map(|t| t.len()).unwrap_or_default();
+                logger::log(&format!("[DEBUG identifiers] {} files changed, {} identifiers", changed, count));
+            }
+            // Stop once the window is gone (we hold the only reference)
+            if Arc::strong_count(&shared) == 1 {
+                return;
+            }
+            std::thread::sleep(REFRESH_INTERVAL);
+        }
+    });
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let source = r#"
+            // parse_comment is ignored
+            fn load_config(path: &str) -> Config {
+                let message = "load_string is ignored";
+                /* block_comment */
+                load_config(path); Config::default()
+            }
+        "#;
+        let counts = tokenize(&RUST, source);
+        assert_eq!(counts.get("load_config"), Some(&2));
+        assert_eq!(counts.get("Config"), Some(&2));
+        assert!(!counts.contains_key("parse_comment"));
+        assert!(!counts.contains_key("load_string"));
+        assert!(!counts.contains_key("block_comment"));
+        assert!(!counts.contains_key("let"));
+
+        // Character literals don't open strings; lifetimes aren't literals
+        let source = "let quote = '\"'; let esc = '\\''; fn get<'a>(after_quote: &'a str) {}";
+        let counts = tokenize(&RUST, source);
+        assert!(counts.contains_key("after_quote"));
+        assert!(counts.contains_key("esc"));
+    }
+
+    #[test]
+    fn test_identifier_query() {
+        assert_eq!(identifier_query("この関数 load_co"), Some("load_co"));
+        assert_eq!(identifier_query("lo"), Some("lo"));
+        assert_eq!(identifier_query(";rvw"), None);
+        assert_eq!(identifier_query("x"), None);
+        assert_eq!(identifier_query("12ab"), None);
+        assert_eq!(identifier_query("@src/ap"), None);
+    }
+}
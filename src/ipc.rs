@@ -0,0 +1,426 @@
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::logger;
+use crate::paths;
+
+/// Requests sent from hook processes (or CLI subcommands) to a resident instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// A Claude Code session started in the terminal the instance is bound to
+    RegisterSession {
+        session_id: String,
+        #[serde(default)]
+        transcript_path: String,
+    },
+    /// Claude finished responding (Stop hook)
+    Stop {
+        session_id: String,
+        #[serde(default)]
+        transcript_path: String,
+    },
+    /// Claude Code sent a notification (Notification hook)
+    Notify { session_id: String, message: String },
+    /// Session info from the statusLine command (shown in the footer)
+    StatusLine { session_id: String, info: StatusLineInfo },
+    /// Ask the user a question (MCP ask_user); the answer is the response message
+    AskUser { question: String },
+    /// Send text to the terminal through the same path as Submit
+    /// `deferred` waits a moment first (the sender is a hook that must exit before the text arrives)
+    Deliver {
+        text: String,
+        #[serde(default)]
+        deferred: bool,
+    },
+    /// Query the instance state (used to wait for completion)
+    Status,
+    /// The asker of a pending question went away (raised by the listener, never sent over the wire)
+    #[serde(skip)]
+    AskClosed,
+}
+
+/// What goes over the wire: a request with the instance's token
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    token: String,
+    request: Request,
+}
+
+/// Model, cost and context usage reported by `moji-bridge statusline`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusLineInfo {
+    pub model: String,
+    pub cwd: String,
+    pub cost_usd: f64,
+    /// Share of the context window in use (None if unknown)
+    pub context_percent: Option<u8>,
+}
+
+/// Snapshot of a resident instance, returned for [`Request::Status`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstanceStatus {
+    /// Number of Stop hooks received so far
+    pub stop_count: u64,
+    /// Transcript of the most recent session, if known
+    #[serde(default)]
+    pub transcript_path: Option<String>,
+}
+
+/// Reply to a [`Request`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<InstanceStatus>,
+}
+
+impl Response {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self { ok: true, message: message.into(), ..Default::default() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self { ok: false, message: message.into(), ..Default::default() }
+    }
+}
+
+/// Registry entry describing a running resident instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub terminal_hwnd: isize,
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub port: u16,
+    /// Secret the instance expects with every request (only readable by the same user)
+    #[serde(default)]
+    pub token: String,
+    pub pid: u32,
+    #[serde(default)]
+    pub sessions: Vec<String>,
+}
+
+impl InstanceInfo {
+    fn registry_path(&self) -> PathBuf {
+        registry_path(self.terminal_hwnd)
+    }
+}
+
+fn registry_path(terminal_hwnd: isize) -> PathBuf {
+    paths::instances_dir().join(format!("{}.json", terminal_hwnd))
+}
+
+/// Write (or overwrite) the registry entry for an instance
+pub fn register_instance(info: &InstanceInfo) -> Result<(), String> {
+    let dir = paths::instances_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let json = serde_json::to_string_pretty(info).map_err(|e| format!("Failed to serialize instance: {}", e))?;
+    std::fs::write(info.registry_path(), json).map_err(|e| format!("Failed to write registry: {}", e))
+}
+
+/// Remove the registry entry for the instance bound to the given terminal
+pub fn unregister_instance(terminal_hwnd: isize) {
+    let _ = std::fs::remove_file(registry_path(terminal_hwnd));
+}
+
+/// List all registered instances (stale entries are cleaned up lazily by [`send`])
+pub fn list_instances() -> Vec<InstanceInfo> {
+    let Ok(entries) = std::fs::read_dir(paths::instances_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect()
+}
+
+/// Find the instance that owns a Claude Code session
+pub fn find_instance_for_session(session_id: &str) -> Option<InstanceInfo> {
+    if session_id.is_empty() {
+        return None;
+    }
+    list_instances()
+        .into_iter()
+        .find(|i| i.sessions.iter().any(|s| s == session_id))
+}
+
+/// Find the instance bound to a terminal window
+pub fn find_instance_for_terminal(terminal_hwnd: isize) -> Option<InstanceInfo> {
+    list_instances()
+        .into_iter()
+        .find(|i| i.terminal_hwnd == terminal_hwnd)
+}
+
+/// Find an instance by name, label or terminal hwnd
+/// With no query, the only running instance is returned (ambiguity is an error)
+pub fn resolve_instance(query: Option<&str>) -> Result<InstanceInfo, String> {
+    let instances = list_instances();
+    let describe = |list: &[InstanceInfo]| {
+        list.iter()
+            .map(|i| match &i.label {
+                Some(label) => format!("{} ({})", i.name, label),
+                None => i.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match query {
+        Some(query) => instances
+            .iter()
+            .find(|i| {
+                i.name == query
+                    || i.label.as_deref() == Some(query)
+                    || i.terminal_hwnd.to_string() == query
+            })
+            .cloned()
+            .ok_or_else(|| format!("No instance named '{}' (running: {})", query, describe(&instances))),
+        None => match instances.len() {
+            0 => Err("No resident instance is running".to_string()),
+            1 => Ok(instances[0].clone()),
+            _ => Err(format!("Several instances are running, pick one with --to: {}", describe(&instances))),
+        },
+    }
+}
+
+/// Send a request to an instance and wait for its response
+/// `timeout` bounds the wait for the response (None waits indefinitely)
+pub fn send(instance: &InstanceInfo, request: &Request, timeout: Option<Duration>) -> Result<Response, String> {
+    send_on(connect(instance)?, instance, request, timeout)
+}
+
+/// Open a connection to an instance (kept by callers that may need to abort the request)
+pub fn connect(instance: &InstanceInfo) -> Result<TcpStream, String> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, instance.port));
+    TcpStream::connect_timeout(&addr, Duration::from_secs(1)).map_err(|e| {
+        // Nobody listening: the instance is gone, drop its entry
+        logger::log(&format!("[DEBUG ipc] Instance {} unreachable ({}), removing entry", instance.name, e));
+        unregister_instance(instance.terminal_hwnd);
+        format!("Instance {} is not running", instance.name)
+    })
+}
+
+/// [`send`] over a connection from [`connect`]; shutting the stream down aborts the wait
+pub fn send_on(
+    mut stream: TcpStream,
+    instance: &InstanceInfo,
+    request: &Request,
+    timeout: Option<Duration>,
+) -> Result<Response, String> {
+    stream.set_read_timeout(timeout).map_err(|e| format!("Failed to set timeout: {}", e))?;
+
+    let envelope = Envelope {
+        token: instance.token.clone(),
+        request: request.clone(),
+    };
+    let mut line = serde_json::to_string(&envelope).map_err(|e| format!("Failed to serialize request: {}", e))?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    if reply.is_empty() {
+        return Err("Connection closed without a response".to_string());
+    }
+    serde_json::from_str(&reply).map_err(|e| format!("Invalid response: {}", e))
+}
+
+// ============================================
+// Resident side
+// ============================================
+
+/// Listener bound at startup and its token, taken by the subscription stream
+static LISTENER: Mutex<Option<(TcpListener, String)>> = Mutex::new(None);
+
+/// Random hex token (seeded from the OS through std's hasher keys)
+fn generate_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+
+    (0..4)
+        .map(|i| {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            hasher.write_u32(i);
+            hasher.write_u128(now.as_nanos());
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// Bind a loopback listener on an ephemeral port and return the port and the token
+/// requests must carry (other local processes can connect, but can't read the registry)
+pub fn bind() -> Result<(u16, String), String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .map_err(|e| format!("Failed to bind IPC listener: {}", e))?;
+    let port = listener.local_addr().map_err(|e| format!("Failed to read IPC port: {}", e))?.port();
+    let token = generate_token();
+    *LISTENER.lock().unwrap() = Some((listener, token.clone()));
+    Ok((port, token))
+}
+
+/// Handle used by the GUI to answer a request
+/// The connection thread waits until this is used or every clone is dropped
+#[derive(Clone)]
+pub struct Reply(Arc<Mutex<Option<std::sync::mpsc::Sender<Response>>>>);
+
+impl Reply {
+    /// Answer the request; false if the asker has gone away (or it was already answered)
+    pub fn send(&self, response: Response) -> bool {
+        match self.0.lock().unwrap().take() {
+            Some(sender) => sender.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Whether the request can no longer be answered
+    pub fn is_closed(&self) -> bool {
+        self.0.lock().unwrap().is_none()
+    }
+}
+
+impl std::fmt::Debug for Reply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Reply")
+    }
+}
+
+/// A request received by the resident instance
+#[derive(Debug, Clone)]
+pub struct Incoming {
+    pub request: Request,
+    pub reply: Reply,
+}
+
+/// Stream of incoming requests for the resident subscription
+pub fn listen() -> impl Stream<Item = Incoming> {
+    iced::stream::channel(16, async |mut output| {
+        let (sender, mut receiver) = mpsc::unbounded();
+        match LISTENER.lock().unwrap().take() {
+            Some((listener, token)) => {
+                thread::spawn(move || accept_loop(listener, token, sender));
+            }
+            None => logger::log("[DEBUG ipc] No listener bound, IPC disabled"),
+        }
+        while let Some(incoming) = receiver.next().await {
+            let _ = output.send(incoming).await;
+        }
+    })
+}
+
+/// Largest request accepted (a delivered prompt may carry file excerpts)
+const MAX_REQUEST_SIZE: u64 = 16 * 1024 * 1024;
+
+/// How long a client may take to send its request line
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a waiting connection checks whether the client is still there
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Wait for the GUI's reply; None if the client closes the connection first
+fn wait_for_reply(stream: &TcpStream, receiver: &std::sync::mpsc::Receiver<Response>) -> Option<Response> {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    loop {
+        match receiver.recv_timeout(CLOSE_CHECK_INTERVAL) {
+            Ok(response) => return Some(response),
+            Err(RecvTimeoutError::Disconnected) => return Some(Response::error("Request was dropped")),
+            Err(RecvTimeoutError::Timeout) => {
+                if stream.set_nonblocking(true).is_err() {
+                    continue;
+                }
+                let mut byte = [0u8; 1];
+                let closed = match stream.peek(&mut byte) {
+                    Ok(0) => true,
+                    Ok(_) => false,
+                    Err(e) => e.kind() != std::io::ErrorKind::WouldBlock,
+                };
+                let _ = stream.set_nonblocking(false);
+                if closed {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+fn accept_loop(listener: TcpListener, token: String, sender: mpsc::UnboundedSender<Incoming>) {
+    let token = Arc::new(token);
+    for stream in listener.incoming().flatten() {
+        let sender = sender.clone();
+        let token = Arc::clone(&token);
+        thread::spawn(move || handle_connection(stream, &token, sender));
+    }
+}
+
+fn handle_connection(stream: TcpStream, token: &str, sender: mpsc::UnboundedSender<Incoming>) {
+    let mut line = String::new();
+    let Ok(read_half) = stream.try_clone() else {
+        return;
+    };
+    // The token is only checked after the line is read, so bound both its size and its time
+    if stream.set_read_timeout(Some(REQUEST_READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let read = BufReader::new(read_half.take(MAX_REQUEST_SIZE)).read_line(&mut line);
+    if read.is_err() || !line.ends_with('\n') {
+        logger::log("[DEBUG ipc] Dropped a connection without a complete request line");
+        return;
+    }
+
+    let response = match serde_json::from_str::<Envelope>(&line) {
+        Ok(envelope) if envelope.token != token => {
+            logger::log("[DEBUG ipc] Rejected request with a wrong token");
+            Response::error("Invalid token")
+        }
+        Ok(Envelope { request, .. }) => {
+            logger::log(&format!("[DEBUG ipc] Received request: {:?}", request));
+            let is_question = matches!(request, Request::AskUser { .. });
+            let (reply_sender, reply_receiver) = std::sync::mpsc::channel();
+            let slot = Arc::new(Mutex::new(Some(reply_sender)));
+            let incoming = Incoming {
+                request,
+                reply: Reply(Arc::clone(&slot)),
+            };
+            if sender.unbounded_send(incoming).is_err() {
+                Response::error("Resident window is shutting down")
+            } else {
+                match wait_for_reply(&stream, &reply_receiver) {
+                    Some(response) => response,
+                    None => {
+                        // The asker gave up (cancelled or exited): nothing to answer anymore
+                        logger::log("[DEBUG ipc] Client closed the connection before the reply");
+                        slot.lock().unwrap().take();
+                        if is_question {
+                            let (closed_sender, _) = std::sync::mpsc::channel();
+                            let _ = sender.unbounded_send(Incoming {
+                                request: Request::AskClosed,
+                                reply: Reply(Arc::new(Mutex::new(Some(closed_sender)))),
+                            });
+                        }
+                        return;
+                    }
+                }
+            }
+        }
+        Err(e) => Response::error(format!("Invalid request: {}", e)),
+    };
+
+    if let Ok(mut json) = serde_json::to_string(&response) {
+        json.push('\n');
+        let mut stream = stream;
+        let _ = stream.write_all(json.as_bytes());
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use crate::normalize;
+
+/// A proofreading rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// ら抜き言葉 (`見れる` for `見られる`)
+    RaNuki,
+    /// The same particle twice (`のの`, `がが`)
+    RepeatedParticle,
+    /// です/ます and だ/である mixed in one text
+    MixedStyle,
+    LongSentence,
+    /// A word typed twice by a conversion slip (`確認確認`)
+    DoubledWord,
+    /// An English word not in the Hunspell dictionary (reported by the spellchecker)
+    Spelling,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Self::RaNuki,
+        Self::RepeatedParticle,
+        Self::MixedStyle,
+        Self::LongSentence,
+        Self::DoubledWord,
+    ];
+}
+
+/// Proofreading settings (global, or replaced per project)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    pub enabled: bool,
+    pub rules: Vec<Rule>,
+    /// Sentences longer than this many characters are flagged
+    pub max_sentence_length: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: Rule::ALL.to_vec(),
+            max_sentence_length: 100,
+        }
+    }
+}
+
+/// A problem found in the text: a byte range within one line, with an optional replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub rule: Rule,
+    pub line: usize,
+    pub range: Range<usize>,
+    pub message: String,
+    pub fix: Option<String>,
+}
+
+/// Verb stems that are commonly written without ら (`見れる` -> `見られる`)
+const RA_NUKI_STEMS: &[&str] = &[
+    "見れ", "来れ", "寝れ", "出れ", "着れ", "居れ", "食べれ", "起きれ", "考えれ", "決めれ", "変えれ", "覚えれ",
+    "調べれ", "止めれ", "始めれ", "続けれ", "受けれ", "投げれ", "開けれ", "閉じれ", "信じれ", "感じれ",
+    "教えれ", "伝えれ", "答えれ", "借りれ", "降りれ", "生きれ", "逃げれ", "助けれ", "集めれ", "比べれ",
+];
+
+/// What may follow a ら抜き stem (`見れば` is fine, `見れる` isn't)
+const RA_NUKI_SUFFIXES: &[&str] = &["る", "ない", "なか", "なく", "ます", "ません", "た", "て", "ず"];
+
+const PARTICLES: &[char] = &['の', 'が', 'を', 'に', 'で', 'は', 'と', 'へ'];
+
+const POLITE_ENDINGS: &[&str] = &["です", "ます", "でした", "ました", "ません", "でしょう", "ましょう", "ください"];
+
+/// Plain-style endings and their polite form (used as the quick fix)
+const PLAIN_ENDINGS: &[(&str, &str)] = &[
+    ("であった", "でした"),
+    ("ではない", "ではありません"),
+    ("である", "です"),
+    ("だった", "でした"),
+    ("だ", "です"),
+];
+
+fn is_hiragana(c: char) -> bool {
+    ('\u{3041}'..='\u{309F}').contains(&c)
+}
+
+fn is_kanji(c: char) -> bool {
+    ('\u{4E00}'..='\u{9FFF}').contains(&c) || c == '々'
+}
+
+fn is_katakana(c: char) -> bool {
+    ('\u{30A1}'..='\u{30FA}').contains(&c) || c == 'ー'
+}
+
+/// Check `text` with the enabled rules; code spans and blocks are skipped
+pub fn lint(text: &str, config: &LintConfig) -> Vec<Issue> {
+    let masked = normalize::mask_code(text);
+
+    let enabled = |rule| config.rules.contains(&rule);
+    let mut issues = Vec::new();
+    for (line_index, line) in masked.split('\n').enumerate() {
+        if enabled(Rule::RaNuki) {
+            ra_nuki(line_index, line, &mut issues);
+        }
+        if enabled(Rule::RepeatedParticle) {
+            repeated_particles(line_index, line, &mut issues);
+        }
+        if enabled(Rule::DoubledWord) {
+            doubled_words(line_index, line, &mut issues);
+        }
+        if enabled(Rule::LongSentence) {
+            long_sentences(line_index, line, config.max_sentence_length, &mut issues);
+        }
+    }
+    if enabled(Rule::MixedStyle) {
+        mixed_style(&masked, &mut issues);
+    }
+    issues.sort_by_key(|issue| (issue.line, issue.range.start));
+    issues
+}
+
+fn ra_nuki(line_index: usize, line: &str, issues: &mut Vec<Issue>) {
+    for stem in RA_NUKI_STEMS {
+        for (start, _) in line.match_indices(stem) {
+            let after = &line[start + stem.len()..];
+            if !RA_NUKI_SUFFIXES.iter().any(|suffix| after.starts_with(suffix)) {
+                continue;
+            }
+            let fixed = format!("{}られ", stem.trim_end_matches('れ'));
+            issues.push(Issue {
+                rule: Rule::RaNuki,
+                line: line_index,
+                range: start..start + stem.len(),
+                message: format!("ら抜き言葉: {} → {}", stem, fixed),
+                fix: Some(fixed),
+            });
+        }
+    }
+}
+
+fn repeated_particles(line_index: usize, line: &str, issues: &mut Vec<Issue>) {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    for window in chars.windows(3) {
+        let [(_, before), (start, first), (_, second)] = window else {
+            continue;
+        };
+        // After hiragana it's likely a word (`ことと`, `ののしる`), not a slip
+        if first == second && PARTICLES.contains(first) && !is_hiragana(*before) && !before.is_whitespace() {
+            issues.push(Issue {
+                rule: Rule::RepeatedParticle,
+                line: line_index,
+                range: *start..start + first.len_utf8() * 2,
+                message: format!("助詞の重複: {}{}", first, second),
+                fix: Some(first.to_string()),
+            });
+        }
+    }
+}
+
+fn doubled_words(line_index: usize, line: &str, issues: &mut Vec<Issue>) {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let byte_at = |i: usize| chars.get(i).map_or(line.len(), |(b, _)| *b);
+    let mut i = 0;
+    while i < chars.len() {
+        let found = (2..=6).rev().find(|&len| {
+            if i + 2 * len > chars.len() {
+                return false;
+            }
+            let word: Vec<char> = chars[i..i + len].iter().map(|(_, c)| *c).collect();
+            let again = chars[i + len..i + 2 * len].iter().map(|(_, c)| *c);
+            // Kanji, or longer katakana (short ones like ドキドキ are often intentional); no hiragana,
+            // and not `一X一X` (一つ一つ, 一人一人 are words)
+            let wordlike = word.iter().all(|&c| is_kanji(c) || is_katakana(c))
+                && (word.iter().any(|&c| is_kanji(c)) || (len >= 3 && word.iter().all(|&c| is_katakana(c))));
+            let one_by_one = len == 2 && word[0] == '一';
+            wordlike && !one_by_one && word.iter().any(|&c| c != word[0]) && again.eq(word.iter().copied())
+        });
+        match found {
+            Some(len) => {
+                let word = &line[byte_at(i)..byte_at(i + len)];
+                issues.push(Issue {
+                    rule: Rule::DoubledWord,
+                    line: line_index,
+                    range: byte_at(i + len)..byte_at(i + 2 * len),
+                    message: format!("語の重複: {}{}", word, word),
+                    fix: Some(String::new()),
+                });
+                i += 2 * len;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+/// Sentences of a line as byte ranges (split after 。！？!?)
+fn sentences(line: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        if matches!(c, '。' | '！' | '？' | '!' | '?' | '．') {
+            let end = i + c.len_utf8();
+            ranges.push(start..end);
+            start = end;
+        }
+    }
+    if !line[start..].trim().is_empty() {
+        ranges.push(start..line.len());
+    }
+    ranges
+}
+
+fn long_sentences(line_index: usize, line: &str, max: usize, issues: &mut Vec<Issue>) {
+    for sentence in sentences(line) {
+        let text = &line[sentence.clone()];
+        let length = text.chars().filter(|c| !c.is_whitespace()).count();
+        if length <= max || text.is_ascii() {
+            continue;
+        }
+        // Mark the part past the limit
+        let over = text.char_indices().filter(|(_, c)| !c.is_whitespace()).nth(max).map_or(0, |(i, _)| i);
+        issues.push(Issue {
+            rule: Rule::LongSentence,
+            line: line_index,
+            range: sentence.start + over..sentence.end,
+            message: format!("長い文（{}文字）", length),
+            fix: None,
+        });
+    }
+}
+
+fn mixed_style(text: &str, issues: &mut Vec<Issue>) {
+    // (line, range of the ending, polite form if plain)
+    let mut polite = Vec::new();
+    let mut plain = Vec::new();
+    for (line_index, line) in text.split('\n').enumerate() {
+        for sentence in sentences(line) {
+            let body = line[sentence.clone()]
+                .trim_end_matches(|c: char| c.is_whitespace() || "。！？!?．」』）)".contains(c));
+            let end = sentence.start + body.len();
+            if let Some(ending) = POLITE_ENDINGS.iter().find(|e| body.ends_with(*e)) {
+                polite.push((line_index, end - ending.len()..end, None));
+            } else if let Some((ending, fix)) = PLAIN_ENDINGS.iter().find(|(e, _)| body.ends_with(e)) {
+                // `だ` after ん/い is a past tense (選んだ, 泳いだ), not the copula
+                let stem = &body[..body.len() - ending.len()];
+                if ending.starts_with('だ') && stem.ends_with(['ん', 'い']) {
+                    continue;
+                }
+                plain.push((line_index, end - ending.len()..end, Some(*fix)));
+            }
+        }
+    }
+    if polite.is_empty() || plain.is_empty() {
+        return;
+    }
+
+    let (minority, message) = if polite.len() >= plain.len() {
+        (plain, "文体の混在: だ・である調（大半は です・ます調）")
+    } else {
+        (polite, "文体の混在: です・ます調（大半は だ・である調）")
+    };
+    for (line, range, fix) in minority {
+        issues.push(Issue {
+            rule: Rule::MixedStyle,
+            line,
+            range,
+            message: message.to_string(),
+            fix: fix.map(str::to_string),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint() {
+        let text = "この画面は見れない。ファイルのの一覧を確認確認します。\n\
+                    修正をお願いします。結果は正常である。`見れる`は対象外です。";
+        let issues = lint(text, &LintConfig::default());
+        let found: Vec<(Rule, usize, &str, Option<&str>)> = issues
+            .iter()
+            .map(|i| {
+                let line = text.split('\n').nth(i.line).unwrap_or_default();
+                (i.rule, i.line, &line[i.range.clone()], i.fix.as_deref())
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Rule::RaNuki, 0, "見れ", Some("見られ")),
+                (Rule::RepeatedParticle, 0, "のの", Some("の")),
+                (Rule::DoubledWord, 0, "確認", Some("")),
+                (Rule::MixedStyle, 1, "である", Some("です")),
+            ]
+        );
+
+        // Words that look doubled and past tenses ending in だ are left alone
+        let text = "一つ一つ確認します。一人一人に聞きます。昨日その本を選んだ。";
+        assert!(lint(text, &LintConfig::default()).is_empty());
+
+        let long = format!("{}。", "あ".repeat(120));
+        let issues = lint(&long, &LintConfig::default());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, Rule::LongSentence);
+    }
+}
@@ -1,11 +1,34 @@
+mod abbreviations;
 mod app;
+mod approval;
+mod batch;
 mod logger;
+mod markdown;
+mod mcp;
+mod normalize;
 mod clipboard_utils;
+mod commands;
+mod completion;
+mod config;
+mod files;
+mod git;
 mod hook;
 mod hotkey;
+mod identifiers;
+mod ipc;
+mod lint;
+mod paste;
+mod paths;
+mod punctuation;
+mod queue;
+mod romaji;
+mod snippets;
+mod spell;
+mod statusline;
 mod terminal;
+mod words;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -39,17 +62,50 @@ fn check_existing_window_for_terminal(_terminal_hwnd: isize) -> bool {
 fn detach_and_spawn_resident(args: &Args) {
     use std::process::{Command, Stdio};
 
-    // Process creation flag to hide console window
+    // Process creation flags: no console, own process group, outside the parent's job
+    const DETACHED_PROCESS: u32 = 0x00000008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+    const CREATE_BREAKAWAY_FROM_JOB: u32 = 0x01000000;
     const CREATE_NO_WINDOW: u32 = 0x08000000;
 
     // STEP 1: Get foreground window IMMEDIATELY (single fast API call)
     // This captures the terminal window before any delays
     let hwnd = terminal::get_foreground_window();
 
+    // SessionStart passes the session on stdin (bounded read, only when piped)
+    let hook_input = if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        None
+    } else {
+        hook::read_hook_input().ok()
+    };
+    let session_id = hook_input
+        .as_ref()
+        .map(|i| i.session_id.clone())
+        .or_else(|| args.session.clone())
+        .filter(|s| !s.is_empty());
+    let cwd = hook_input
+        .as_ref()
+        .map(|i| i.cwd.clone())
+        .or_else(|| args.cwd.clone())
+        .filter(|s| !s.is_empty());
+    let transcript_path = hook_input
+        .as_ref()
+        .map(|i| i.transcript_path.clone())
+        .or_else(|| args.transcript.clone())
+        .filter(|s| !s.is_empty());
+
     // STEP 2: Check if MojiBridge window for this terminal already exists
     if let Some(h) = hwnd {
         if check_existing_window_for_terminal(h) {
             logger::log(&format!("[DEBUG detach] MojiBridge window for terminal {} already exists, skipping spawn", h));
+            // Let the existing instance know about the new session (for Stop hook routing)
+            if let (Some(session_id), Some(instance)) = (session_id, ipc::find_instance_for_terminal(h)) {
+                let request = ipc::Request::RegisterSession {
+                    session_id,
+                    transcript_path: transcript_path.unwrap_or_default(),
+                };
+                let _ = ipc::send(&instance, &request, Some(std::time::Duration::from_secs(1)));
+            }
             return;
         }
     }
@@ -69,30 +125,36 @@ fn detach_and_spawn_resident(args: &Args) {
         resident_args.push("--terminal-hwnd".to_string());
         resident_args.push(h.to_string());
     }
+    if let Some(session_id) = session_id {
+        resident_args.push("--session".to_string());
+        resident_args.push(session_id);
+    }
+    if let Some(cwd) = cwd {
+        resident_args.push("--cwd".to_string());
+        resident_args.push(cwd);
+    }
+    if let Some(transcript_path) = transcript_path {
+        resident_args.push("--transcript".to_string());
+        resident_args.push(transcript_path);
+    }
 
-    // STEP 4: Spawn using PowerShell Start-Process for true detachment
-    // Format arguments as PowerShell array: 'arg1','arg2','arg3'
-    let args_str = resident_args
-        .iter()
-        .map(|s| format!("'{}'", s))
-        .collect::<Vec<_>>()
-        .join(",");
-    let ps_command = format!(
-        "Start-Process '{}' -ArgumentList {} -WindowStyle Hidden",
-        exe_path.display(),
-        args_str
-    );
-
-    logger::log(&format!("[DEBUG detach] PowerShell command: {}", ps_command));
-
-    let mut cmd = Command::new("powershell");
-    cmd.args(["-WindowStyle", "Hidden", "-Command", &ps_command]);
-    cmd.creation_flags(CREATE_NO_WINDOW);
-    cmd.stdin(Stdio::null());
-    cmd.stdout(Stdio::null());
-    cmd.stderr(Stdio::null());
-
-    let _ = cmd.spawn();
+    // STEP 4: Spawn the resident directly (no shell, so paths and IDs need no quoting)
+    logger::log(&format!("[DEBUG detach] Spawning {} {:?}", exe_path.display(), resident_args));
+
+    let spawn = |flags: u32| {
+        let mut cmd = Command::new(&exe_path);
+        cmd.args(&resident_args);
+        cmd.creation_flags(flags);
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::null());
+        cmd.stderr(Stdio::null());
+        cmd.spawn()
+    };
+    // Leave Claude Code's job object when allowed, so the resident outlives the hook
+    let flags = DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW;
+    if spawn(flags | CREATE_BREAKAWAY_FROM_JOB).is_err() {
+        let _ = spawn(flags);
+    }
     // Don't log after spawn - exit immediately
 }
 
@@ -118,6 +180,10 @@ struct Args {
     #[arg(long)]
     cwd: Option<String>,
 
+    /// Transcript of the session (lets batch sends detect idleness before any Stop hook)
+    #[arg(long)]
+    transcript: Option<String>,
+
     /// Custom label for the session
     #[arg(long)]
     label: Option<String>,
@@ -129,6 +195,19 @@ struct Args {
     /// Detach mode: spawn resident process and exit immediately
     #[arg(long)]
     detach: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Send a file of prompts one by one, waiting for Claude to finish each
+    Batch(batch::BatchArgs),
+    /// Claude Code statusLine command (also mirrors the status to the resident window)
+    Statusline,
+    /// MCP stdio server providing an ask_user tool answered in the resident window
+    Mcp(mcp::McpArgs),
 }
 
 fn main() {
@@ -138,6 +217,19 @@ fn main() {
     let args = Args::parse();
     logger::log(&format!("[DEBUG main] args.resident={}, args.detach={}", args.resident, args.detach));
 
+    if let Some(command) = &args.command {
+        let result = match command {
+            Command::Batch(batch_args) => batch::run(batch_args),
+            Command::Statusline => statusline::run(),
+            Command::Mcp(mcp_args) => mcp::run(mcp_args),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Detach mode: spawn resident process and exit immediately
     // No need for init_terminal_tracking() here - we use get_foreground_window() directly
     if args.detach {
@@ -170,9 +262,15 @@ fn main() {
 
         let config = app::ResidentConfig {
             terminal_hwnd,
+            label: args.label.clone(),
+            session: args.session.clone(),
+            cwd: args.cwd.clone(),
+            transcript: args.transcript.clone(),
         };
 
-        if let Err(e) = app::run_resident_gui(config) {
+        let result = app::run_resident_gui(config);
+        ipc::unregister_instance(terminal_hwnd.unwrap_or(0));
+        if let Err(e) = result {
             eprintln!("Error running GUI: {}", e);
             std::process::exit(1);
         }
@@ -181,8 +279,46 @@ fn main() {
         // Try to read hook input from stdin
         logger::log("[DEBUG main] Non-resident mode, reading hook input");
         match hook::read_hook_input() {
+            Ok(input) if matches!(input.hook_event_name.as_str(), "Stop" | "Notification") => {
+                // Stop: send the next queued prompt, Notification: show a banner
+                if let Err(e) = hook::forward_to_resident(&input) {
+                    logger::log(&format!("[DEBUG main] {} not forwarded: {}", input.hook_event_name, e));
+                }
+            }
+            Ok(input) if input.hook_event_name == "PreToolUse" => {
+                // PreToolUse hook: ask for approval in a dialog (IME-friendly reason field)
+                logger::log(&format!("[DEBUG main] PreToolUse for tool: {}", input.tool_name));
+                if let Err(e) = approval::run_approval_gui(&input) {
+                    eprintln!("Error running GUI: {}", e);
+                    std::process::exit(1);
+                }
+            }
             Ok(input) => {
                 logger::log(&format!("[DEBUG main] Hook input received, user_prompt: {}", input.user_prompt));
+                // `//name args`: expand the stored template (works without the GUI, e.g. over SSH)
+                // Without a template of that name, `//...` is the input helper trigger as before
+                let cwd = Some(input.cwd.as_str()).filter(|c| !c.is_empty());
+                let template = hook::template_invocation(&input.user_prompt)
+                    .and_then(|(name, args)| snippets::find_snippet(name, cwd).map(|snippet| (name, args, snippet)));
+                if let Some((name, template_args, snippet)) = template {
+                    logger::log(&format!("[DEBUG main] Expanding template: {}", name));
+                    let text = snippets::expand_invocation(&snippet, template_args, cwd);
+                    // Block `//name` and send the expanded text as the prompt; without a resident
+                    // instance (e.g. over SSH) nothing could send it again, and a hook can't replace
+                    // the prompt, so `//name` goes through with the text as additional context
+                    let result = match hook::redeliver_template(&input, &text) {
+                        Ok(()) => hook::write_block_output(&format!("//{} expanded; sending the template", name)),
+                        Err(e) => {
+                            logger::log(&format!("[DEBUG main] Template not re-delivered: {}", e));
+                            hook::write_template_output(name, &text)
+                        }
+                    };
+                    if let Err(e) = result {
+                        eprintln!("Error writing hook output: {}", e);
+                        std::process::exit(1);
+                    }
+                    return;
+                }
                 // Check if the prompt is a trigger
                 if hook::is_trigger(&input.user_prompt) {
                     logger::log("[DEBUG main] Is trigger, reading clipboard");
@@ -220,7 +356,12 @@ fn main() {
             }
             Err(e) => {
                 logger::log(&format!("[DEBUG main] Hook input error: {}", e));
-                // No input or invalid input, just run the GUI directly
+                // Called as a hook with unreadable input: exit without output, so Claude Code
+                // carries on as if there were no hook (a PreToolUse gets its normal permission prompt)
+                if !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                    return;
+                }
+                // Started by hand without input, just run the GUI directly
                 // This is useful for testing without Claude Code
                 if let Err(e) = app::run_gui() {
                     eprintln!("Error running GUI: {}", e);
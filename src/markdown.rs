@@ -0,0 +1,468 @@
+use crate::files;
+
+/// A piece of HTML: a start/end tag (lowercase name, raw attributes) or text
+enum Token<'a> {
+    Open(String, &'a str),
+    Close(String),
+    Text(&'a str),
+}
+
+/// Split HTML into tags and text, dropping comments, doctypes and processing instructions
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        // Find the closing `>`, ignoring any inside quoted attribute values
+        let mut quote = None;
+        let end = rest.char_indices().skip(1).find_map(|(i, c)| {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), _) if q == c => quote = None,
+                (None, '>') => return Some(i),
+                _ => {}
+            }
+            None
+        });
+        let Some(end) = end else {
+            // A lone `<` in text
+            tokens.push(Token::Text(rest));
+            break;
+        };
+
+        let tag = &rest[1..end];
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let name_end = tag.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        if closing {
+            tokens.push(Token::Close(name));
+        } else if !name.is_empty() {
+            tokens.push(Token::Open(name, &tag[name_end..]));
+        } else {
+            tokens.push(Token::Text(&rest[..=end]));
+        }
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+/// Value of attribute `name` in a tag's raw attribute text
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(i) = rest.to_ascii_lowercase().find(name) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[i + name.len()..].trim_start();
+        rest = &rest[i + name.len()..];
+        if !before.is_none_or(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Replace the common named entities and numeric character references
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut output = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').filter(|&end| end <= 10).map(|end| &rest[1..=end]);
+        let decoded = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, decoded) {
+            (Some(entity), Some(c)) => {
+                output.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// A table being collected (cells are rendered once the column count is known)
+#[derive(Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+    cell: Option<String>,
+}
+
+/// Markdown written so far, plus the nesting state of the HTML being converted
+#[derive(Default)]
+struct Writer {
+    output: String,
+    /// Open lists: ordered flag and the number of items so far
+    lists: Vec<(bool, usize)>,
+    /// Text of an open `<pre>` and its language
+    pre: Option<(String, String)>,
+    table: Option<Table>,
+    /// Open links: href and where their text starts
+    links: Vec<(String, usize)>,
+    /// Start offsets of open blockquotes
+    quotes: Vec<usize>,
+    /// Open `<b>`/`<strong>` tags and whether each one emitted `**`
+    bold: Vec<bool>,
+    /// Depth inside `<script>`, `<style>` and the like (their text is dropped)
+    skip: usize,
+    /// Whether anything beyond plain paragraphs was seen
+    structured: bool,
+}
+
+impl Writer {
+    /// Where inline text goes: the current table cell, or the output
+    fn target(&mut self) -> &mut String {
+        match self.table.as_mut().and_then(|t| t.cell.as_mut()) {
+            Some(cell) => cell,
+            None => &mut self.output,
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.skip > 0 {
+            return;
+        }
+        let text = decode_entities(text);
+        if let Some((pre, _)) = &mut self.pre {
+            pre.push_str(&text);
+            return;
+        }
+        // Whitespace between table rows and cells
+        if self.table.as_ref().is_some_and(|t| t.cell.is_none()) {
+            return;
+        }
+        // Collapse whitespace like a browser; no leading space at the start of a line
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let target = self.target();
+        if text.starts_with(char::is_whitespace) && !target.is_empty() && !target.ends_with(char::is_whitespace) {
+            target.push(' ');
+        }
+        target.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+            target.push(' ');
+        }
+    }
+
+    /// End the current line
+    fn newline(&mut self) {
+        let output = &mut self.output;
+        while output.ends_with(' ') {
+            output.pop();
+        }
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+    }
+
+    /// Start a new block (blank line), or just a new line inside a list
+    fn block(&mut self) {
+        // `<li><p>`: the paragraph stays on the item's line
+        let line = self.output.rsplit('\n').next().unwrap_or_default().trim_start();
+        let numbered = line.strip_suffix(". ").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if !self.lists.is_empty() && (line == "- " || numbered) {
+            return;
+        }
+        self.newline();
+        if self.lists.is_empty() && !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn open(&mut self, name: &str, attributes: &str) {
+        match name {
+            "script" | "style" | "head" | "title" | "noscript" => self.skip += 1,
+            _ if self.skip > 0 => {}
+            "br" => match (&mut self.pre, &mut self.table) {
+                (Some((pre, _)), _) => pre.push('\n'),
+                (None, Some(_)) => self.target().push(' '),
+                (None, None) => self.newline(),
+            },
+            // `<pre><code class="language-rust">`
+            "code" if self.pre.is_some() => {
+                if let Some((_, language)) = self.pre.as_mut().filter(|(_, l)| l.is_empty()) {
+                    *language = language_of(attributes);
+                }
+            }
+            _ if self.pre.is_some() => {}
+            "p" | "div" | "section" | "article" | "header" | "footer" if self.table.is_none() => self.block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.structured = true;
+                self.block();
+                let level = name[1..].parse().unwrap_or(1);
+                self.output.push_str(&format!("{} ", "#".repeat(level)));
+            }
+            "ul" | "ol" => {
+                self.structured = true;
+                if self.lists.is_empty() {
+                    self.block();
+                }
+                self.lists.push((name == "ol", 0));
+            }
+            "li" => {
+                self.newline();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some((true, count)) => {
+                        *count += 1;
+                        format!("{}. ", count)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.output.push_str(&"  ".repeat(depth));
+                self.output.push_str(&marker);
+            }
+            "pre" => {
+                self.structured = true;
+                self.block();
+                self.pre = Some((String::new(), language_of(attributes)));
+            }
+            "code" => self.target().push('`'),
+            "strong" | "b" => {
+                // Google Docs wraps everything in <b style="font-weight:normal">
+                let bold = !attributes.contains("font-weight:normal");
+                if bold {
+                    self.target().push_str("**");
+                }
+                self.bold.push(bold);
+            }
+            "em" | "i" => self.target().push('*'),
+            "a" => {
+                let href = attribute(attributes, "href").unwrap_or_default();
+                let start = self.target().len();
+                self.links.push((href, start));
+            }
+            "blockquote" => {
+                self.structured = true;
+                self.block();
+                self.quotes.push(self.output.len());
+            }
+            "hr" => {
+                self.block();
+                self.output.push_str("---");
+                self.block();
+            }
+            "table" => {
+                self.structured = true;
+                self.block();
+                self.table = Some(Table::default());
+            }
+            "tr" => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            "td" | "th" => {
+                if let Some(table) = &mut self.table {
+                    table.cell = Some(String::new());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "script" | "style" | "head" | "title" | "noscript" => self.skip = self.skip.saturating_sub(1),
+            _ if self.skip > 0 => {}
+            "pre" => {
+                if let Some((text, language)) = self.pre.take() {
+                    self.output.push_str(&files::fenced_block(&language, &text));
+                    self.block();
+                }
+            }
+            // Tags inside `<pre>` (usually `<code>`) are part of the fenced block
+            _ if self.pre.is_some() => {}
+            "code" => self.target().push('`'),
+            "strong" | "b" => {
+                let opened = self.bold.pop() == Some(true);
+                if opened {
+                    self.target().push_str("**");
+                }
+            }
+            "em" | "i" => self.target().push('*'),
+            "p" | "div" | "section" | "article" | "header" | "footer" if self.table.is_none() => self.block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.block(),
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block();
+                }
+            }
+            "li" => self.newline(),
+            "a" => {
+                let Some((href, start)) = self.links.pop() else {
+                    return;
+                };
+                let target = self.target();
+                let label = target.split_off(start.min(target.len()));
+                let label = label.trim();
+                if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") || label == href {
+                    target.push_str(label);
+                } else {
+                    target.push_str(&format!("[{}]({})", label, href));
+                    self.structured = true;
+                }
+            }
+            "blockquote" => {
+                let Some(start) = self.quotes.pop() else {
+                    return;
+                };
+                let quoted = self.output.split_off(start.min(self.output.len()));
+                for line in quoted.trim_end().lines() {
+                    self.output.push_str(if line.is_empty() { ">" } else { "> " });
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+                self.block();
+            }
+            "td" | "th" => {
+                if let Some(table) = &mut self.table {
+                    let cell = table.cell.take().unwrap_or_default();
+                    let cell = cell.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|");
+                    if let Some(row) = table.rows.last_mut() {
+                        row.push(cell);
+                    }
+                }
+            }
+            "table" => {
+                if let Some(table) = self.table.take() {
+                    self.output.push_str(&render_table(&table.rows));
+                    self.block();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Language from a `class="language-rust"` (or `lang-`) attribute
+fn language_of(attributes: &str) -> String {
+    attribute(attributes, "class")
+        .unwrap_or_default()
+        .split_whitespace()
+        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Pipe table with the first row as the header
+fn render_table(rows: &[Vec<String>]) -> String {
+    let rows: Vec<&Vec<String>> = rows.iter().filter(|r| !r.is_empty()).collect();
+    let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let line = |cells: &[String]| {
+        let mut cells = cells.to_vec();
+        cells.resize(columns, String::new());
+        format!("| {} |\n", cells.join(" | "))
+    };
+
+    let mut output = line(rows[0]);
+    output.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+    for row in &rows[1..] {
+        output.push_str(&line(row));
+    }
+    output
+}
+
+/// Convert clipboard HTML to Markdown (headings, lists, tables, `<pre>` as fenced blocks, links)
+/// Returns None when the HTML has no such structure, so plain text can be pasted as-is
+pub fn html_to_markdown(html: &str) -> Option<String> {
+    // Windows CF_HTML: the copied part sits between fragment markers
+    let html = match (html.find("<!--StartFragment-->"), html.find("<!--EndFragment-->")) {
+        (Some(start), Some(end)) if start < end => &html[start + "<!--StartFragment-->".len()..end],
+        _ => html.find('<').map_or(html, |start| &html[start..]),
+    };
+
+    let mut writer = Writer::default();
+    for token in tokenize(html) {
+        match token {
+            Token::Open(name, attributes) => writer.open(&name, attributes),
+            Token::Close(name) => writer.close(&name),
+            Token::Text(text) => writer.push_text(text),
+        }
+    }
+    // An unterminated `<pre>` still keeps its text
+    writer.close("pre");
+
+    if !writer.structured {
+        return None;
+    }
+    let lines: Vec<&str> = writer.output.lines().map(str::trim_end).collect();
+    let mut markdown = lines.join("\n");
+    while markdown.contains("\n\n\n") {
+        markdown = markdown.replace("\n\n\n", "\n\n");
+    }
+    Some(markdown.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = "<h2>手順</h2><ol><li>ビルド<ul><li><code>cargo build</code></li></ul></li>\
+                    <li><a href=\"https://example.com/doc\">資料</a>を読む</li></ol>\
+                    <table><tr><th>名前</th><th>値</th></tr><tr><td>a|b</td><td>1 &amp; 2</td></tr></table>\
+                    <pre><code class=\"language-rust\">fn main() {\n    println!(\"&lt;hi&gt;\");\n}</code></pre>";
+        let expected = "## 手順\n\n\
+                        1. ビルド\n  - `cargo build`\n\
+                        2. [資料](https://example.com/doc)を読む\n\n\
+                        | 名前 | 値 |\n| --- | --- |\n| a\\|b | 1 & 2 |\n\n\
+                        ```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```";
+        assert_eq!(html_to_markdown(html).as_deref(), Some(expected));
+
+        let plain = "Version:0.9\r\n<html><body><!--StartFragment--><span>ただの文章</span><!--EndFragment--></body></html>";
+        assert_eq!(html_to_markdown(plain), None);
+    }
+}
@@ -0,0 +1,298 @@
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::ipc;
+use crate::logger;
+use crate::terminal;
+
+/// Protocol version answered when the client doesn't propose one
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Give up on a question nobody answers (the window then drops it as well)
+const ASK_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Connections of `ask_user` calls waiting for an answer, by JSON-RPC id
+/// (shut down when Claude cancels the call, and all of them when stdin closes)
+#[derive(Default)]
+struct Calls {
+    streams: HashMap<String, TcpStream>,
+    /// Claude has gone away: calls that start now are aborted right away
+    closed: bool,
+}
+
+type InFlight = Mutex<Calls>;
+
+/// Abort every waiting call and any that starts later (Claude exited or restarted the server)
+fn close_all(in_flight: &InFlight) {
+    let mut calls = in_flight.lock().unwrap();
+    calls.closed = true;
+    for (_, stream) in calls.streams.drain() {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Arguments for `moji-bridge mcp`
+#[derive(Args, Debug)]
+pub struct McpArgs {
+    /// Instance that shows the questions (name, label or terminal hwnd)
+    /// Defaults to the instance bound to this terminal
+    #[arg(long)]
+    pub to: Option<String>,
+}
+
+/// Pick the resident instance for this MCP server
+/// The server gets no session id, so match on the terminal we run in
+fn resolve_instance(args: &McpArgs) -> Result<ipc::InstanceInfo, String> {
+    if args.to.is_some() {
+        return ipc::resolve_instance(args.to.as_deref());
+    }
+    terminal::init_terminal_tracking();
+    let terminal_hwnd = terminal::get_terminal_pid().and_then(terminal::get_window_by_pid);
+    match terminal_hwnd.and_then(ipc::find_instance_for_terminal) {
+        Some(instance) => Ok(instance),
+        None => ipc::resolve_instance(None),
+    }
+}
+
+fn tool_definitions() -> Value {
+    json!([{
+        "name": "ask_user",
+        "description": "Ask the user a clarifying question. The question is shown in the MojiBridge \
+                        window and the user's typed answer (often Japanese) is returned.",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question to show to the user"
+                }
+            },
+            "required": ["question"]
+        }
+    }])
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+/// Handle `ask_user`: show the question in the resident window and wait for the answer
+/// Returns None if Claude cancelled the call meanwhile (no response is sent then)
+fn ask_user(args: &McpArgs, arguments: &Value, id: &Value, in_flight: &InFlight) -> Option<Value> {
+    let Some(question) = arguments.get("question").and_then(Value::as_str) else {
+        return Some(tool_result("Missing 'question' argument".to_string(), true));
+    };
+    let instance = match resolve_instance(args) {
+        Ok(instance) => instance,
+        Err(e) => return Some(tool_result(e, true)),
+    };
+    let request = ipc::Request::AskUser {
+        question: question.to_string(),
+    };
+    let stream = match ipc::connect(&instance) {
+        Ok(stream) => stream,
+        Err(e) => return Some(tool_result(e, true)),
+    };
+    if let Ok(handle) = stream.try_clone() {
+        let mut calls = in_flight.lock().unwrap();
+        if calls.closed {
+            let _ = handle.shutdown(Shutdown::Both);
+        }
+        calls.streams.insert(id.to_string(), handle);
+    }
+    // The user may take a while to type the answer
+    let result = ipc::send_on(stream, &instance, &request, Some(ASK_TIMEOUT));
+    if in_flight.lock().unwrap().streams.remove(&id.to_string()).is_none() {
+        logger::log(&format!("[DEBUG mcp] ask_user {} was cancelled", id));
+        return None;
+    }
+    Some(match result {
+        Ok(response) if response.ok => tool_result(response.message, false),
+        Ok(response) => tool_result(response.message, true),
+        Err(e) => tool_result(e, true),
+    })
+}
+
+/// Abort a call Claude no longer waits for (`notifications/cancelled`)
+fn cancel(params: &Value, in_flight: &InFlight) {
+    let Some(id) = params.get("requestId") else {
+        return;
+    };
+    if let Some(stream) = in_flight.lock().unwrap().streams.remove(&id.to_string()) {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Handle one JSON-RPC message; returns the response (None for notifications and cancelled calls)
+fn handle_message(args: &McpArgs, message: &Value, in_flight: &InFlight) -> Option<Value> {
+    let method = message.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    if method == "notifications/cancelled" {
+        cancel(&params, in_flight);
+    }
+    let id = message.get("id")?.clone();
+
+    let result = match method {
+        "initialize" => {
+            let version = params
+                .get("protocolVersion")
+                .and_then(Value::as_str)
+                .unwrap_or(PROTOCOL_VERSION);
+            json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "moji-bridge", "version": env!("CARGO_PKG_VERSION") },
+            })
+        }
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tool_definitions() }),
+        "tools/call" => {
+            let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+            let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+            match name {
+                "ask_user" => ask_user(args, &arguments, &id, in_flight)?,
+                _ => tool_result(format!("Unknown tool: {}", name), true),
+            }
+        }
+        _ => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+            }));
+        }
+    };
+
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// Run `moji-bridge mcp`: a Model Context Protocol server over stdio
+pub fn run(args: &McpArgs) -> Result<(), String> {
+    logger::log("[DEBUG mcp] MCP server started");
+    serve(args, io::stdin().lock())
+}
+
+/// Answer JSON-RPC messages from `input` until it ends
+fn serve(args: &McpArgs, input: impl BufRead) -> Result<(), String> {
+    let in_flight = InFlight::default();
+
+    // Tool calls run on their own threads, so a cancellation can arrive while one waits
+    thread::scope(|scope| {
+        let result = (|| {
+            for line in input.lines() {
+                let line = line.map_err(|e| format!("Failed to read stdin: {}", e))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let message = match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        write_message(&json!({
+                            "jsonrpc": "2.0",
+                            "id": Value::Null,
+                            "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+                        }))?;
+                        continue;
+                    }
+                };
+                if message.get("method").and_then(Value::as_str) == Some("tools/call") {
+                    let in_flight = &in_flight;
+                    scope.spawn(move || {
+                        if let Some(response) = handle_message(args, &message, in_flight) {
+                            if let Err(e) = write_message(&response) {
+                                logger::log(&format!("[DEBUG mcp] {}", e));
+                            }
+                        }
+                    });
+                } else if let Some(response) = handle_message(args, &message, &in_flight) {
+                    write_message(&response)?;
+                }
+            }
+            Ok(())
+        })();
+        // Nobody can receive the answers anymore: don't keep the scope (and the window's
+        // question) waiting for ASK_TIMEOUT
+        logger::log("[DEBUG mcp] stdin closed, aborting waiting calls");
+        close_all(&in_flight);
+        result
+    })
+}
+
+/// Write one JSON-RPC message to stdout
+fn write_message(message: &Value) -> Result<(), String> {
+    let mut handle = io::stdout().lock();
+    writeln!(handle, "{}", message).map_err(|e| format!("Failed to write to stdout: {}", e))?;
+    handle.flush().map_err(|e| format!("Failed to flush stdout: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_message() {
+        let args = McpArgs { to: None };
+        let in_flight = InFlight::default();
+
+        let init = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-06-18"}});
+        let response = handle_message(&args, &init, &in_flight).unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2025-06-18");
+
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(handle_message(&args, &notification, &in_flight).is_none());
+
+        let list = json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"});
+        let response = handle_message(&args, &list, &in_flight).unwrap();
+        assert_eq!(response["result"]["tools"][0]["name"], "ask_user");
+
+        let unknown = json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list"});
+        let response = handle_message(&args, &unknown, &in_flight).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+
+        // Cancelling a waiting ask_user closes its connection
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        in_flight.lock().unwrap().streams.insert(json!(4).to_string(), stream);
+        let cancelled = json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 4}});
+        assert!(handle_message(&args, &cancelled, &in_flight).is_none());
+        assert!(in_flight.lock().unwrap().streams.is_empty());
+    }
+
+    #[test]
+    fn test_stdin_closed_during_ask() {
+        // An instance that takes the question and never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let instance = ipc::InstanceInfo {
+            terminal_hwnd: -(std::process::id() as isize),
+            name: format!("mcp-test-{}", std::process::id()),
+            label: None,
+            port: listener.local_addr().unwrap().port(),
+            token: String::new(),
+            pid: std::process::id(),
+            sessions: Vec::new(),
+        };
+        ipc::register_instance(&instance).unwrap();
+        let silent = thread::spawn(move || listener.accept().map(|(stream, _)| stream));
+
+        // Claude asks, then exits: stdin ends right after the call
+        let args = McpArgs { to: Some(instance.name.clone()) };
+        let input = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call",
+            "params": {"name": "ask_user", "arguments": {"question": "?"}}});
+        let started = std::time::Instant::now();
+        let result = serve(&args, io::Cursor::new(format!("{}\n", input)));
+        ipc::unregister_instance(instance.terminal_hwnd);
+
+        assert!(result.is_ok());
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(silent.join().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+/// Which normalizations are applied to a prompt before it is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Normalization {
+    /// Full-width letters and digits to ASCII (`ｍａｉｎ２` -> `main2`), like NFKC
+    pub fullwidth_alphanumerics: bool,
+    /// Half-width katakana to full-width (`ｶﾞｲﾄﾞ` -> `ガイド`), outside code
+    pub halfwidth_katakana: bool,
+    /// Ideographic spaces (U+3000) to ASCII spaces inside code spans and blocks
+    pub code_ideographic_space: bool,
+    /// Full-width tilde `～` to the wave dash `〜`, outside code
+    pub wave_dash: bool,
+    /// Minus and hyphen look-alikes (`−`, `－`, `‐`) to ASCII `-`
+    pub minus: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            fullwidth_alphanumerics: true,
+            halfwidth_katakana: true,
+            code_ideographic_space: true,
+            wave_dash: true,
+            minus: true,
+        }
+    }
+}
+
+/// Full-width katakana for U+FF61..=U+FF9D, in code point order
+const HALFWIDTH_KATAKANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+/// Split text into (is_code, part): fenced blocks and inline code spans are code, the rest prose
+pub fn split_code(text: &str) -> Vec<(bool, &str)> {
+    let mut parts = Vec::new();
+    let mut prose_start = 0;
+    let mut fence: Option<(usize, &str)> = None; // (block start, fence marker)
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker_len = |c: char| trimmed.len() - trimmed.trim_start_matches(c).len();
+        match fence {
+            None => {
+                let marker = match (marker_len('`'), marker_len('~')) {
+                    (n, _) if n >= 3 => Some(&trimmed[..n]),
+                    (_, n) if n >= 3 => Some(&trimmed[..n]),
+                    _ => None,
+                };
+                if let Some(marker) = marker {
+                    split_inline(&text[prose_start..offset], &mut parts);
+                    fence = Some((offset, marker));
+                }
+            }
+            Some((start, marker)) => {
+                let closes = trimmed.starts_with(marker) && trimmed.trim_end().trim_start_matches(&marker[..1]).is_empty();
+                if closes {
+                    let end = offset + line.len();
+                    parts.push((true, &text[start..end]));
+                    prose_start = end;
+                    fence = None;
+                }
+            }
+        }
+        offset += line.len();
+    }
+
+    match fence {
+        // An unclosed fence runs to the end of the text
+        Some((start, _)) => parts.push((true, &text[start..])),
+        None => split_inline(&text[prose_start..], &mut parts),
+    }
+    parts.retain(|(_, part)| !part.is_empty());
+    parts
+}
+
+/// Split prose at inline code spans (a backtick run closed by a run of the same length)
+fn split_inline<'a>(text: &'a str, parts: &mut Vec<(bool, &'a str)>) {
+    let mut rest = text;
+    while let Some(open) = rest.find('`') {
+        let run = rest[open..].len() - rest[open..].trim_start_matches('`').len();
+        let after = &rest[open + run..];
+
+        // Find a closing run of exactly the same length
+        let mut search = 0;
+        let close = loop {
+            let Some(found) = after[search..].find('`') else {
+                break None;
+            };
+            let start = search + found;
+            let len = after[start..].len() - after[start..].trim_start_matches('`').len();
+            if len == run {
+                break Some(start);
+            }
+            search = start + len;
+        };
+
+        match close {
+            Some(close) => {
+                parts.push((false, &rest[..open]));
+                let end = open + run + close + run;
+                parts.push((true, &rest[open..end]));
+                rest = &rest[end..];
+            }
+            None => {
+                // Unmatched backticks are literal text
+                parts.push((false, &rest[..open + run]));
+                rest = &rest[open + run..];
+            }
+        }
+    }
+    parts.push((false, rest));
+}
+
+/// Blank out code (keeping byte offsets and line breaks) so prose checks don't look at it
+pub fn mask_code(text: &str) -> String {
+    split_code(text)
+        .into_iter()
+        .map(|(code, part)| {
+            if !code {
+                return part.to_string();
+            }
+            part.chars()
+                .map(|c| if c == '\n' { "\n".to_string() } else { " ".repeat(c.len_utf8()) })
+                .collect()
+        })
+        .collect()
+}
+
+/// Apply the enabled normalizations
+pub fn normalize(text: &str, options: &Normalization) -> String {
+    let mut output = String::with_capacity(text.len());
+    for (code, part) in split_code(text) {
+        let mut chars = part.chars().peekable();
+        while let Some(c) = chars.next() {
+            let mapped = match c {
+                'Ａ'..='Ｚ' | 'ａ'..='ｚ' | '０'..='９' if options.fullwidth_alphanumerics => {
+                    char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)
+                }
+                '\u{FF61}'..='\u{FF9F}' if options.halfwidth_katakana && !code => {
+                    let mark = chars.next_if(|&next| next == 'ﾞ' || next == 'ﾟ');
+                    output.push_str(&widen_katakana(c, mark));
+                    continue;
+                }
+                '\u{3000}' if options.code_ideographic_space && code => ' ',
+                '～' if options.wave_dash && !code => '〜',
+                '−' | '－' | '‐' | '‑' | '﹣' if options.minus => '-',
+                _ => c,
+            };
+            output.push(mapped);
+        }
+    }
+    output
+}
+
+/// Full-width form of a half-width katakana, combined with a following (han)dakuten
+fn widen_katakana(c: char, mark: Option<char>) -> String {
+    let wide = match c {
+        'ﾞ' => '゛',
+        'ﾟ' => '゜',
+        _ => HALFWIDTH_KATAKANA.chars().nth(c as usize - 0xFF61).unwrap_or(c),
+    };
+    let combined = match mark {
+        Some('ﾞ') if wide == 'ウ' => Some('ヴ'),
+        Some('ﾞ') if "カキクケコサシスセソタチツテトハヒフヘホ".contains(wide) => char::from_u32(wide as u32 + 1),
+        Some('ﾟ') if "ハヒフヘホ".contains(wide) => char::from_u32(wide as u32 + 2),
+        _ => None,
+    };
+    match (combined, mark) {
+        (Some(combined), _) => combined.to_string(),
+        (None, Some(mark)) => format!("{}{}", wide, widen_katakana(mark, None)),
+        (None, None) => wide.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let text = "ｐａｒｓｅＩｄ２を直して ｶﾞｲﾄﾞ～ﾊﾟｽ `let\u{3000}x = ｆ(−1)`\n```\nｶﾅ\u{3000}～\n```\n１０時";
+        assert_eq!(
+            normalize(text, &Normalization::default()),
+            "parseId2を直して ガイド〜パス `let x = f(-1)`\n```\nｶﾅ ～\n```\n10時"
+        );
+
+        let parts: Vec<bool> = split_code("a ``b ` c`` d `e").iter().map(|(code, _)| *code).collect();
+        assert_eq!(parts, vec![false, true, false, false]);
+    }
+}
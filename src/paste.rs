@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+use crate::normalize;
+
+/// Logs longer than this many lines are cut down to their head and tail
+const TRUNCATE_ABOVE_LINES: usize = 200;
+const HEAD_LINES: usize = 60;
+const TAIL_LINES: usize = 60;
+
+/// A cleanup applied to multi-line text pasted into the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteTransform {
+    StripLineNumbers,
+    StripPrompts,
+    Dedent,
+    TrimTrailing,
+    CollapseBlankLines,
+    TruncateLog,
+}
+
+impl PasteTransform {
+    /// All transforms, in the order they are applied
+    pub const ALL: [PasteTransform; 6] = [
+        Self::StripLineNumbers,
+        Self::StripPrompts,
+        Self::Dedent,
+        Self::TrimTrailing,
+        Self::CollapseBlankLines,
+        Self::TruncateLog,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::StripLineNumbers => "Line numbers",
+            Self::StripPrompts => "Shell prompts",
+            Self::Dedent => "Dedent",
+            Self::TrimTrailing => "Trailing spaces",
+            Self::CollapseBlankLines => "Blank lines",
+            Self::TruncateLog => "Truncate long logs",
+        }
+    }
+
+    fn apply(self, lines: Vec<String>) -> Vec<String> {
+        match self {
+            Self::StripLineNumbers => strip_line_numbers(lines),
+            Self::StripPrompts => lines.into_iter().map(|l| strip_prompt(&l).to_string()).collect(),
+            Self::Dedent => dedent(lines),
+            Self::TrimTrailing => {
+                let mut lines: Vec<String> = lines.into_iter().map(|l| l.trim_end().to_string()).collect();
+                while lines.last().is_some_and(|l| l.is_empty()) {
+                    lines.pop();
+                }
+                lines
+            }
+            Self::CollapseBlankLines => {
+                let mut collapsed: Vec<String> = Vec::new();
+                for line in lines {
+                    let blank = line.trim().is_empty();
+                    if !(blank && collapsed.last().is_some_and(|l| l.trim().is_empty())) {
+                        collapsed.push(if blank { String::new() } else { line });
+                    }
+                }
+                collapsed
+            }
+            Self::TruncateLog => truncate_log(lines),
+        }
+    }
+}
+
+/// Apply the enabled transforms (in `PasteTransform::ALL` order)
+pub fn apply(text: &str, enabled: &[PasteTransform]) -> String {
+    let mut lines: Vec<String> = text.replace("\r\n", "\n").lines().map(str::to_string).collect();
+    for transform in PasteTransform::ALL.into_iter().filter(|t| enabled.contains(t)) {
+        lines = transform.apply(lines);
+    }
+    lines.join("\n")
+}
+
+/// Apply the transforms inside the fenced code blocks of Markdown only (a stack trace in a
+/// `<pre>` pasted from a web page), leaving the prose, lists and quotes alone
+pub fn apply_to_fences(markdown: &str, enabled: &[PasteTransform]) -> String {
+    normalize::split_code(markdown)
+        .into_iter()
+        .map(|(code, part)| match fence_parts(part).filter(|_| code) {
+            Some((open, body, close)) => format!("{}\n{}\n{}", open, apply(body, enabled), close),
+            None => part.to_string(),
+        })
+        .collect()
+}
+
+/// (opening fence line, body, closing fence and the rest) of a fenced block
+fn fence_parts(block: &str) -> Option<(&str, &str, &str)> {
+    let (open, rest) = block.split_once('\n')?;
+    let marker = &open.trim_start()[..3.min(open.trim_start().len())];
+    if marker != "```" && marker != "~~~" {
+        return None;
+    }
+    let body_end = rest.trim_end_matches('\n').rfind('\n').map_or(0, |i| i + 1);
+    let close = &rest[body_end..];
+    if close.trim_start().starts_with(marker) {
+        Some((open, rest[..body_end].trim_end_matches('\n'), close))
+    } else {
+        // Unclosed at the end of the text
+        Some((open, rest.trim_end_matches('\n'), ""))
+    }
+}
+
+/// Remove a gutter like `  12 | code` or `12:code`, but only when every line has one and the
+/// numbers count up by one (so a pasted numbered list is left alone)
+fn strip_line_numbers(lines: Vec<String>) -> Vec<String> {
+    // (line number, gutter length in bytes)
+    let gutter = |line: &str| -> Option<(u64, usize)> {
+        let trimmed = line.trim_start();
+        let digits = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+        let number = trimmed[..digits].parse().ok()?;
+        let rest = &trimmed[digits..];
+        let padded = rest.trim_start_matches(' ');
+        let rest = match padded.chars().next() {
+            Some(c @ ('|' | ':' | '│')) => {
+                let after = &padded[c.len_utf8()..];
+                after.strip_prefix(' ').unwrap_or(after)
+            }
+            _ if rest.is_empty() => rest,
+            _ => rest.strip_prefix(['\t', ' '])?,
+        };
+        Some((number, line.len() - rest.len()))
+    };
+
+    let gutters: Vec<Option<(u64, usize)>> = lines.iter().map(|l| gutter(l)).collect();
+    let numbered: Vec<(u64, usize)> = gutters.iter().flatten().copied().collect();
+    let counts_up = numbered.windows(2).all(|w| w[1].0 == w[0].0 + 1);
+    let all_numbered = lines.iter().zip(&gutters).all(|(l, g)| g.is_some() || l.trim().is_empty());
+    if numbered.len() < 2 || !counts_up || !all_numbered {
+        return lines;
+    }
+    lines
+        .into_iter()
+        .zip(gutters)
+        .map(|(line, gutter)| match gutter {
+            Some((_, len)) => line[len..].to_string(),
+            None => line,
+        })
+        .collect()
+}
+
+/// The line without a leading shell prompt (`$ `, `❯ `, `>>> `, `PS C:\dir> `, `user@host:~$ `)
+fn strip_prompt(line: &str) -> &str {
+    for prompt in ["$ ", "❯ ", ">>> "] {
+        if let Some(rest) = line.strip_prefix(prompt) {
+            return rest;
+        }
+    }
+    if line.starts_with("PS ") {
+        if let Some(end) = line.find("> ") {
+            return &line[end + 2..];
+        }
+    }
+    // `user@host:~/dir$ ` (no spaces before the `$`)
+    if let Some(end) = line.find("$ ") {
+        let prompt = &line[..end];
+        if prompt.contains('@') && !prompt.contains(char::is_whitespace) {
+            return &line[end + 2..];
+        }
+    }
+    line
+}
+
+/// Remove the indentation shared by all non-blank lines
+fn dedent(lines: Vec<String>) -> Vec<String> {
+    let indent = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let common = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| &l[..indent(l)])
+        .reduce(|a, b| {
+            let shared = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+            &a[..shared]
+        })
+        .map_or(0, str::len);
+    lines
+        .into_iter()
+        .map(|line| line.get(common..).unwrap_or_default().to_string())
+        .collect()
+}
+
+/// Keep the head and tail of a long log with a marker for the lines in between
+fn truncate_log(mut lines: Vec<String>) -> Vec<String> {
+    if lines.len() <= TRUNCATE_ABOVE_LINES {
+        return lines;
+    }
+    let tail = lines.split_off(lines.len() - TAIL_LINES);
+    let omitted = lines.len() - HEAD_LINES;
+    lines.truncate(HEAD_LINES);
+    lines.push(format!("... ({} lines omitted) ...", omitted));
+    lines.extend(tail);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let trace = "  10 |     let x = parse()?;  \n  11 |     \n  12 | \n  13 |     run(x);";
+        assert_eq!(apply(trace, &PasteTransform::ALL), "let x = parse()?;\n\nrun(x);");
+
+        let session = "user@host:~/app$ cargo test\n   Compiling app\n$ echo ok";
+        assert_eq!(
+            apply(session, &[PasteTransform::StripPrompts]),
+            "cargo test\n   Compiling app\necho ok"
+        );
+
+        // A numbered list isn't a gutter
+        let list = "1. first\n3. third";
+        assert_eq!(apply(list, &PasteTransform::ALL), list);
+
+        // In Markdown from HTML only the code blocks are cleaned
+        let markdown = "> 1. quoted\n\n```\n  10 | panic!()  \n  11 | at main\n```\n$ not a prompt here\n";
+        assert_eq!(
+            apply_to_fences(markdown, &PasteTransform::ALL),
+            "> 1. quoted\n\n```\npanic!()\nat main\n```\n$ not a prompt here\n"
+        );
+
+        let log: Vec<String> = (1..=300).map(|n| format!("line {}", n)).collect();
+        let truncated = apply(&log.join("\n"), &[PasteTransform::TruncateLog]);
+        assert_eq!(truncated.lines().count(), HEAD_LINES + 1 + TAIL_LINES);
+        assert!(truncated.contains("... (180 lines omitted) ..."));
+    }
+}
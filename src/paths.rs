@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+/// Per-user data directory for persistent state (queue, settings, ...)
+/// Windows: %APPDATA%\moji-bridge, others: $XDG_DATA_HOME or ~/.local/share
+pub fn data_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+    };
+    base.unwrap_or_else(std::env::temp_dir).join("moji-bridge")
+}
+
+/// Per-project settings directory (`<cwd>/.moji-bridge`)
+pub fn project_dir(cwd: &str) -> PathBuf {
+    Path::new(cwd).join(".moji-bridge")
+}
+
+/// Where pasted clipboard images are saved for a session (`<data dir>/attachments/<session>`)
+pub fn attachments_dir(session: Option<&str>) -> PathBuf {
+    data_dir().join("attachments").join(file_key(session.unwrap_or_default()))
+}
+
+/// Directory where running resident instances register themselves
+/// Lives in temp so stale entries don't survive a reboot
+pub fn instances_dir() -> PathBuf {
+    std::env::temp_dir().join("moji-bridge").join("instances")
+}
+
+/// FNV-1a hash of a string (stable across runs and builds, unlike `DefaultHasher`)
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Turn an arbitrary string (e.g. a working directory) into a safe file stem
+/// The readable part drops non-ASCII characters, so a hash of the full value keeps
+/// `C:\work\アプリ` and `C:\work\ツール` apart
+pub fn file_key(value: &str) -> String {
+    let key: String = value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let key = key.trim_matches('_');
+    let key = if key.is_empty() { "default" } else { key };
+    format!("{}-{:016x}", key, fnv1a(value))
+}
+
+/// The user's home directory (USERPROFILE on Windows, HOME elsewhere)
+pub fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(var).map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_key() {
+        let app = file_key(r"C:\work\アプリ");
+        let tool = file_key(r"C:\work\ツール");
+        assert!(app.starts_with("C__work-"));
+        assert_ne!(app, tool);
+        assert_ne!(app, file_key(r"C:\work"));
+        assert_eq!(app, file_key(r"C:\work\アプリ"));
+    }
+}
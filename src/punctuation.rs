@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use crate::normalize;
+
+/// Japanese comma and period style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommaStyle {
+    #[default]
+    Keep,
+    /// `、` `。`
+    Touten,
+    /// `，` `．`
+    Comma,
+}
+
+/// Quote style for Japanese text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteStyle {
+    #[default]
+    Keep,
+    /// `「」`
+    Kagi,
+    /// `""`
+    Double,
+}
+
+/// Parenthesis width around Japanese text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParenStyle {
+    #[default]
+    Keep,
+    /// `（）`
+    Fullwidth,
+    /// `()`
+    Halfwidth,
+}
+
+/// A team's punctuation conventions (code spans and blocks are never touched)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PunctuationProfile {
+    pub comma: CommaStyle,
+    pub quotes: QuoteStyle,
+    pub parentheses: ParenStyle,
+    /// Also convert the prompt when it is sent (otherwise only on demand with Ctrl+P)
+    pub on_send: bool,
+}
+
+impl PunctuationProfile {
+    /// Whether the profile changes anything at all
+    pub fn is_active(&self) -> bool {
+        self.comma != CommaStyle::Keep || self.quotes != QuoteStyle::Keep || self.parentheses != ParenStyle::Keep
+    }
+}
+
+/// Convert the prose parts of `text` to the profile's punctuation
+pub fn convert(text: &str, profile: &PunctuationProfile) -> String {
+    normalize::split_code(text)
+        .into_iter()
+        .map(|(code, part)| if code { part.to_string() } else { convert_prose(part, profile) })
+        .collect()
+}
+
+/// Converted text of `selection` (byte range of `text`): code is found in the whole text, so a
+/// selection starting inside a code span or block doesn't mistake the code for prose
+pub fn convert_selection(text: &str, selection: Range<usize>, profile: &PunctuationProfile) -> String {
+    let mut output = String::new();
+    let mut offset = 0;
+    for (code, part) in normalize::split_code(text) {
+        let part_range = offset..offset + part.len();
+        offset = part_range.end;
+        let start = part_range.start.max(selection.start);
+        let end = part_range.end.min(selection.end);
+        if start >= end {
+            continue;
+        }
+        let slice = &text[start..end];
+        output.push_str(&if code { slice.to_string() } else { convert_prose(slice, profile) });
+    }
+    output
+}
+
+fn convert_prose(text: &str, profile: &PunctuationProfile) -> String {
+    let mut text: String = text
+        .chars()
+        .map(|c| match (profile.comma, c) {
+            (CommaStyle::Touten, '，') => '、',
+            (CommaStyle::Touten, '．') => '。',
+            (CommaStyle::Comma, '、') => '，',
+            (CommaStyle::Comma, '。') => '．',
+            _ => c,
+        })
+        .collect();
+
+    text = match profile.quotes {
+        QuoteStyle::Keep => text,
+        QuoteStyle::Kagi => {
+            let text = convert_pairs(&text, ('“', '”'), ('「', '」'), true);
+            convert_pairs(&text, ('"', '"'), ('「', '」'), true)
+        }
+        QuoteStyle::Double => convert_pairs(&text, ('「', '」'), ('"', '"'), false),
+    };
+
+    match profile.parentheses {
+        ParenStyle::Keep => text,
+        ParenStyle::Fullwidth => convert_pairs(&text, ('(', ')'), ('（', '）'), true),
+        ParenStyle::Halfwidth => convert_pairs(&text, ('（', '）'), ('(', ')'), false),
+    }
+}
+
+/// Replace matched `from` pairs with `to`; with `japanese_only`, only pairs enclosing
+/// non-ASCII text (so `f(x)` and `"key"` in prose stay as they are)
+fn convert_pairs(text: &str, from: (char, char), to: (char, char), japanese_only: bool) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    let mut open: Vec<usize> = Vec::new();
+
+    for i in 0..chars.len() {
+        let c = chars[i];
+        // With identical open/close characters (`"`), an open one pending means this one closes
+        let closes = c == from.1 && (from.0 != from.1 || !open.is_empty());
+        if closes {
+            let Some(start) = open.pop() else {
+                continue;
+            };
+            if !japanese_only || chars[start + 1..i].iter().any(|c| !c.is_ascii()) {
+                chars[start] = to.0;
+                chars[i] = to.1;
+            }
+        } else if c == from.0 {
+            open.push(i);
+        }
+    }
+    chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let profile = PunctuationProfile {
+            comma: CommaStyle::Comma,
+            quotes: QuoteStyle::Kagi,
+            parentheses: ParenStyle::Fullwidth,
+            on_send: false,
+        };
+        let text = "設定は\"有効\"にして、f(x) を呼ぶ(必須)。`a、b` と \"key\"。";
+        assert_eq!(convert(text, &profile), "設定は「有効」にして，f(x) を呼ぶ（必須）．`a、b` と \"key\"．");
+
+        let back = PunctuationProfile {
+            comma: CommaStyle::Touten,
+            quotes: QuoteStyle::Double,
+            parentheses: ParenStyle::Halfwidth,
+            on_send: false,
+        };
+        assert_eq!(convert("「値」（任意），以上．", &back), "\"値\"(任意)、以上。");
+
+        // Only the selection is converted, with code found in the whole text
+        let text = "`a、b` と c、d";
+        let start = text.find('b').unwrap_or_default();
+        assert_eq!(convert_selection(text, start..text.len(), &profile), "b` と c，d");
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::logger;
+use crate::paths;
+
+/// Prompts waiting to be sent when Claude finishes its current turn
+/// Persisted per working directory so the queue survives restarts
+#[derive(Debug, Default)]
+pub struct PromptQueue {
+    items: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QueueFile {
+    items: Vec<String>,
+}
+
+impl PromptQueue {
+    /// Load the queue for a working directory (None uses a shared default queue)
+    pub fn load(cwd: Option<&str>) -> Self {
+        let path = paths::data_dir()
+            .join("queues")
+            .join(format!("{}.json", paths::file_key(cwd.unwrap_or(""))));
+        let items = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<QueueFile>(&json).ok())
+            .map(|file| file.items)
+            .unwrap_or_default();
+        Self { items, path: Some(path) }
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn push(&mut self, prompt: String) {
+        self.items.push(prompt);
+        self.save();
+    }
+
+    /// Put a prompt back at the head of the queue (e.g. after a failed send)
+    pub fn push_front(&mut self, prompt: String) {
+        self.items.insert(0, prompt);
+        self.save();
+    }
+
+    /// Take the next prompt to send
+    pub fn pop_front(&mut self) -> Option<String> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let item = self.items.remove(0);
+        self.save();
+        Some(item)
+    }
+
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.items.len() {
+            self.items.swap(index, index - 1);
+            self.save();
+        }
+    }
+
+    pub fn move_down(&mut self, index: usize) {
+        if index + 1 < self.items.len() {
+            self.items.swap(index, index + 1);
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.items.remove(index);
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let file = QueueFile { items: self.items.clone() };
+        match serde_json::to_string_pretty(&file) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(path, json) {
+                    logger::log(&format!("[DEBUG queue] Failed to save queue: {}", e));
+                }
+            }
+            Err(e) => logger::log(&format!("[DEBUG queue] Failed to serialize queue: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_reorder() {
+        let mut queue = PromptQueue::default();
+        queue.push("a".to_string());
+        queue.push("b".to_string());
+        queue.push("c".to_string());
+
+        queue.move_up(2);
+        assert_eq!(queue.items(), ["a", "c", "b"]);
+        queue.move_down(0);
+        assert_eq!(queue.items(), ["c", "a", "b"]);
+        queue.move_up(0);
+        queue.move_down(2);
+        assert_eq!(queue.items(), ["c", "a", "b"]);
+
+        assert_eq!(queue.pop_front().as_deref(), Some("c"));
+        queue.remove(1);
+        assert_eq!(queue.items(), ["a"]);
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+/// Fallback Japanese input (for when the system IME is broken or missing)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Off,
+    Hiragana,
+    Katakana,
+}
+
+impl Mode {
+    /// Off -> Hiragana -> Katakana -> Off (Ctrl+J)
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Hiragana,
+            Self::Hiragana => Self::Katakana,
+            Self::Katakana => Self::Off,
+        }
+    }
+
+    /// Short label for the status indicator
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "A",
+            Self::Hiragana => "あ",
+            Self::Katakana => "ア",
+        }
+    }
+}
+
+/// Romaji -> hiragana, including punctuation typed on an ASCII keyboard
+const TABLE: &[(&str, &str)] = &[
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("sa", "さ"), ("si", "し"), ("shi", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("ta", "た"), ("ti", "ち"), ("chi", "ち"), ("tu", "つ"), ("tsu", "つ"), ("te", "て"), ("to", "と"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("ha", "は"), ("hi", "ひ"), ("hu", "ふ"), ("fu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("ya", "や"), ("yu", "ゆ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("wa", "わ"), ("wi", "うぃ"), ("we", "うぇ"), ("wo", "を"),
+    ("nn", "ん"), ("n'", "ん"), ("xn", "ん"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("za", "ざ"), ("zi", "じ"), ("ji", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("de", "で"), ("do", "ど"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("va", "ゔぁ"), ("vi", "ゔぃ"), ("vu", "ゔ"), ("ve", "ゔぇ"), ("vo", "ゔぉ"),
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kyo", "きょ"),
+    ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"), ("sha", "しゃ"), ("shu", "しゅ"), ("she", "しぇ"), ("sho", "しょ"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"), ("cha", "ちゃ"), ("chu", "ちゅ"), ("che", "ちぇ"), ("cho", "ちょ"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nyo", "にょ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hyo", "ひょ"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("myo", "みょ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("ryo", "りょ"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gyo", "ぎょ"),
+    ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"), ("ja", "じゃ"), ("ju", "じゅ"), ("je", "じぇ"), ("jo", "じょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"),
+    ("dya", "ぢゃ"), ("dyu", "ぢゅ"), ("dyo", "ぢょ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("byo", "びょ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pyo", "ぴょ"),
+    ("fa", "ふぁ"), ("fi", "ふぃ"), ("fe", "ふぇ"), ("fo", "ふぉ"),
+    ("thi", "てぃ"), ("dhi", "でぃ"), ("twu", "とぅ"), ("dwu", "どぅ"),
+    ("xa", "ぁ"), ("xi", "ぃ"), ("xu", "ぅ"), ("xe", "ぇ"), ("xo", "ぉ"),
+    ("la", "ぁ"), ("li", "ぃ"), ("lu", "ぅ"), ("le", "ぇ"), ("lo", "ぉ"),
+    ("xya", "ゃ"), ("xyu", "ゅ"), ("xyo", "ょ"), ("lya", "ゃ"), ("lyu", "ゅ"), ("lyo", "ょ"),
+    ("xtu", "っ"), ("xtsu", "っ"), ("ltu", "っ"), ("ltsu", "っ"), ("xwa", "ゎ"),
+    ("-", "ー"), (",", "、"), (".", "。"), ("[", "「"), ("]", "」"), ("/", "・"), ("~", "〜"),
+];
+
+/// Readings and words offered on Space, after the user's own entries
+const DEFAULT_WORDS: &[(&str, &str)] = &[
+    ("かくにん", "確認"), ("しゅうせい", "修正"), ("へんこう", "変更"), ("ついか", "追加"), ("さくじょ", "削除"),
+    ("じっこう", "実行"), ("せつめい", "説明"), ("ないよう", "内容"), ("もんだい", "問題"), ("げんいん", "原因"),
+    ("けっか", "結果"), ("ほうほう", "方法"), ("ひつよう", "必要"), ("かのう", "可能"), ("たいおう", "対応"),
+    ("じっそう", "実装"), ("せってい", "設定"), ("かんすう", "関数"), ("へんすう", "変数"), ("ひきすう", "引数"),
+    ("もどりち", "戻り値"), ("かた", "型"), ("ふぁいる", "ファイル"), ("てすと", "テスト"), ("えらー", "エラー"),
+    ("ばぐ", "バグ"), ("しよう", "仕様"), ("しよう", "使用"), ("りかい", "理解"), ("ていあん", "提案"),
+    ("けんとう", "検討"), ("いらい", "依頼"), ("ほぞん", "保存"), ("さいしょ", "最初"), ("さいご", "最後"),
+    ("いか", "以下"), ("いじょう", "以上"), ("ぜんたい", "全体"), ("ぶぶん", "部分"), ("ばあい", "場合"),
+    ("りゆう", "理由"), ("もくてき", "目的"), ("せっけい", "設計"), ("こうぞう", "構造"), ("しょり", "処理"),
+    ("にゅうりょく", "入力"), ("しゅつりょく", "出力"), ("ひょうじ", "表示"), ("がめん", "画面"), ("きのう", "機能"),
+    ("きのう", "昨日"), ("きょう", "今日"), ("あした", "明日"), ("おねがい", "お願い"), ("ありがとう", "有難う"),
+    ("わたし", "私"), ("なに", "何"), ("なぜ", "何故"), ("こーど", "コード"), ("れびゅー", "レビュー"),
+];
+
+/// Convert romaji to hiragana; the unconverted tail (`k`, `ky`, a lone `n`) is returned separately
+/// unless `flush` is set, in which case a trailing `n` becomes `ん` and the rest is kept as is
+pub fn to_kana(romaji: &str, flush: bool) -> (String, String) {
+    let chars: Vec<char> = romaji.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut kana = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let matched = (1..=4.min(chars.len() - i)).rev().find_map(|len| {
+            let key: String = chars[i..i + len].iter().collect();
+            TABLE.iter().find(|(romaji, _)| *romaji == key).map(|(_, kana)| (len, *kana))
+        });
+        let next = chars.get(i + 1).copied();
+        match (chars[i], matched) {
+            (_, Some((len, converted))) => {
+                kana.push_str(converted);
+                i += len;
+                continue;
+            }
+            // `n` before a consonant (`kanji`), or at the end when flushing
+            ('n', None) if next.is_some_and(|n| !"aiueoyn'".contains(n)) || (next.is_none() && flush) => kana.push('ん'),
+            // A doubled consonant is a small tsu (`kitto`)
+            (c, None) if next == Some(c) && c.is_ascii_alphabetic() && !"aiueon".contains(c) => kana.push('っ'),
+            (c, None) => {
+                let rest: String = chars[i..].iter().collect();
+                if !flush && TABLE.iter().any(|(romaji, _)| romaji.starts_with(&rest)) {
+                    return (kana, rest);
+                }
+                kana.push(c);
+            }
+        }
+        i += 1;
+    }
+    (kana, String::new())
+}
+
+/// Hiragana to katakana (other characters are kept)
+pub fn to_katakana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ぁ'..='ゖ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Text being composed: romaji typed so far, and kanji candidates once Space is pressed
+#[derive(Debug, Clone, Default)]
+pub struct Preedit {
+    pub romaji: String,
+    pub candidates: Vec<String>,
+    /// Index of the shown candidate (None while showing kana)
+    pub selected: Option<usize>,
+}
+
+impl Preedit {
+    pub fn is_empty(&self) -> bool {
+        self.romaji.is_empty()
+    }
+
+    /// The kana for the typed romaji (a pending `k` or `ky` stays latin)
+    pub fn kana(&self, mode: Mode) -> String {
+        let (kana, pending) = to_kana(&self.romaji, false);
+        let kana = format!("{}{}", kana, pending);
+        match mode {
+            Mode::Katakana => to_katakana(&kana),
+            _ => kana,
+        }
+    }
+
+    /// What is shown under the underline and inserted on commit
+    pub fn text(&self, mode: Mode) -> String {
+        match self.selected.and_then(|i| self.candidates.get(i)) {
+            Some(candidate) => candidate.clone(),
+            None => match mode {
+                Mode::Katakana => to_katakana(&to_kana(&self.romaji, true).0),
+                _ => to_kana(&self.romaji, true).0,
+            },
+        }
+    }
+
+    /// Show the next candidate for the reading (Space); false if there are none
+    pub fn next_candidate(&mut self, dictionary: &BTreeMap<String, Vec<String>>) -> bool {
+        if self.candidates.is_empty() {
+            self.candidates = lookup(dictionary, &to_kana(&self.romaji, true).0);
+        }
+        if self.candidates.is_empty() {
+            return false;
+        }
+        self.selected = Some(self.selected.map_or(0, |i| (i + 1) % self.candidates.len()));
+        true
+    }
+
+    /// Back to kana when a candidate is shown, otherwise remove the last typed character
+    pub fn backspace(&mut self) {
+        if self.selected.is_some() {
+            self.selected = None;
+        } else {
+            self.romaji.pop();
+            self.candidates.clear();
+        }
+    }
+}
+
+/// Words for a hiragana reading: the user's dictionary first, then the built-in one
+pub fn lookup(dictionary: &BTreeMap<String, Vec<String>>, reading: &str) -> Vec<String> {
+    let mut words: Vec<String> = dictionary.get(reading).cloned().unwrap_or_default();
+    for (_, word) in DEFAULT_WORDS.iter().filter(|(r, _)| *r == reading) {
+        if !words.iter().any(|w| w == word) {
+            words.push(word.to_string());
+        }
+    }
+    // The reading itself (and its katakana) so Space can cycle back to kana
+    if !words.is_empty() {
+        words.push(reading.to_string());
+        words.push(to_katakana(reading));
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_kana() {
+        assert_eq!(to_kana("kanjiwohenkan", true), ("かんじをへんかん".to_string(), String::new()));
+        assert_eq!(to_kana("kitto,kyo-", false), ("きっと、きょー".to_string(), String::new()));
+        assert_eq!(to_kana("shinky", false), ("しん".to_string(), "ky".to_string()));
+        assert_eq!(to_kana("kon'ya", false).0, "こんや");
+        assert_eq!(to_katakana("てすとふぁいる"), "テストファイル");
+
+        let mut preedit = Preedit {
+            romaji: "kakunin".to_string(),
+            ..Default::default()
+        };
+        let dictionary = BTreeMap::from([("かくにん".to_string(), vec!["確認".to_string(), "核人".to_string()])]);
+        assert!(preedit.next_candidate(&dictionary));
+        assert_eq!(preedit.text(Mode::Hiragana), "確認");
+        preedit.next_candidate(&dictionary);
+        assert_eq!(preedit.text(Mode::Hiragana), "核人");
+        preedit.backspace();
+        assert_eq!(preedit.text(Mode::Katakana), "カクニン");
+    }
+}
@@ -0,0 +1,236 @@
+use std::path::{Path, PathBuf};
+
+use crate::clipboard_utils;
+use crate::commands;
+use crate::git;
+use crate::paths;
+
+/// A prompt template from a snippet directory
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub name: String,
+    pub description: String,
+    pub body: String,
+}
+
+/// A `${N:default}` placeholder in expanded snippet text (byte offsets of the default text)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tabstop {
+    pub index: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Snippet directories: global (`<data dir>/snippets`) and per-project (`<cwd>/.moji-bridge/snippets`)
+/// Later directories override earlier ones by name
+pub fn snippet_dirs(cwd: Option<&str>) -> Vec<PathBuf> {
+    let mut dirs = vec![paths::data_dir().join("snippets")];
+    if let Some(cwd) = cwd {
+        dirs.push(paths::project_dir(cwd).join("snippets"));
+    }
+    dirs
+}
+
+/// Read all `*.md` snippets, sorted by name
+pub fn load_snippets(cwd: Option<&str>) -> Vec<Snippet> {
+    let mut snippets: Vec<Snippet> = Vec::new();
+    for dir in snippet_dirs(cwd) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_none_or(|e| e != "md") {
+                continue;
+            }
+            let (Some(name), Ok(content)) = (
+                path.file_stem().and_then(|s| s.to_str()),
+                std::fs::read_to_string(&path),
+            ) else {
+                continue;
+            };
+            let content = content.replace("\r\n", "\n");
+            let (fields, body) = commands::parse_frontmatter(&content);
+            let description = fields
+                .into_iter()
+                .find(|(key, _)| key == "description")
+                .map(|(_, value)| value)
+                .unwrap_or_default();
+            let snippet = Snippet {
+                name: name.to_string(),
+                description,
+                body: body.trim_end().to_string(),
+            };
+            snippets.retain(|s| s.name != snippet.name);
+            snippets.push(snippet);
+        }
+    }
+    snippets.sort_by(|a, b| a.name.cmp(&b.name));
+    snippets
+}
+
+/// Today's date in local time (YYYY-MM-DD)
+#[cfg(windows)]
+fn today() -> String {
+    use windows::Win32::System::SystemInformation::GetLocalTime;
+    let now = unsafe { GetLocalTime() };
+    format!("{:04}-{:02}-{:02}", now.wYear, now.wMonth, now.wDay)
+}
+
+#[cfg(not(windows))]
+fn today() -> String {
+    std::process::Command::new("date")
+        .arg("+%Y-%m-%d")
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Replace `{date}`, `{cwd}`, `{git_branch}` and `{clipboard}` (only the ones used are looked up)
+pub fn expand_variables(text: &str, cwd: Option<&str>) -> String {
+    let mut text = text.to_string();
+    if text.contains("{date}") {
+        text = text.replace("{date}", &today());
+    }
+    if text.contains("{cwd}") {
+        text = text.replace("{cwd}", cwd.unwrap_or_default());
+    }
+    if text.contains("{git_branch}") {
+        let branch = cwd.and_then(|cwd| git::current_branch(Path::new(cwd)));
+        text = text.replace("{git_branch}", branch.as_deref().unwrap_or_default());
+    }
+    if text.contains("{clipboard}") {
+        let clipboard = clipboard_utils::read_from_clipboard().unwrap_or_default();
+        text = text.replace("{clipboard}", &clipboard);
+    }
+    text
+}
+
+/// Strip `${N:default}` / `${N}` placeholders, keeping the default text
+/// Returns the plain text and the tabstops in visiting order (`$0`-style index 0 comes last)
+pub fn parse_placeholders(text: &str) -> (String, Vec<Tabstop>) {
+    let mut output = String::new();
+    let mut tabstops = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let digits = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+        // (index, default text, bytes consumed after `${`)
+        let placeholder = after[..digits].parse::<u32>().ok().and_then(|index| {
+            let tail = &after[digits..];
+            if let Some(tail) = tail.strip_prefix(':') {
+                let end = tail.find('}')?;
+                Some((index, &tail[..end], digits + end + 2))
+            } else {
+                tail.starts_with('}').then_some((index, "", digits + 1))
+            }
+        });
+
+        match placeholder {
+            Some((index, default, consumed)) => {
+                let start = output.len();
+                output.push_str(default);
+                tabstops.push(Tabstop { index, start, end: output.len() });
+                rest = &after[consumed..];
+            }
+            None => {
+                // Not a placeholder: keep `${` literally
+                output.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+
+    tabstops.sort_by_key(|t| (t.index == 0, t.index, t.start));
+    (output, tabstops)
+}
+
+/// Rewrite the text between tabstop boundaries with `f`, shifting the tabstops to match
+/// (substituted text can't create placeholders because they are already parsed)
+fn map_segments(text: &str, tabstops: &mut [Tabstop], mut f: impl FnMut(&str) -> String) -> String {
+    let mut bounds: Vec<usize> = tabstops.iter().flat_map(|t| [t.start, t.end]).collect();
+    bounds.push(text.len());
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut output = String::new();
+    let mut moved = vec![(0, 0)];
+    let mut previous = 0;
+    for bound in bounds {
+        output.push_str(&f(&text[previous..bound]));
+        moved.push((bound, output.len()));
+        previous = bound;
+    }
+    let new_offset = |offset: usize| moved.iter().find(|(old, _)| *old == offset).map_or(offset, |(_, new)| *new);
+    for tabstop in tabstops.iter_mut() {
+        tabstop.start = new_offset(tabstop.start);
+        tabstop.end = new_offset(tabstop.end);
+    }
+    output
+}
+
+/// Text to insert for a snippet, with its tabstops
+/// Placeholders are parsed before variables are substituted, so `${1:..}` in the clipboard stays text
+pub fn expand(snippet: &Snippet, cwd: Option<&str>) -> (String, Vec<Tabstop>) {
+    let (text, mut tabstops) = parse_placeholders(&snippet.body);
+    let text = map_segments(&text, &mut tabstops, |segment| expand_variables(segment, cwd));
+    (text, tabstops)
+}
+
+/// Find a snippet by name (project snippets take precedence)
+pub fn find_snippet(name: &str, cwd: Option<&str>) -> Option<Snippet> {
+    load_snippets(cwd).into_iter().find(|s| s.name == name)
+}
+
+/// Plain text for a `//name args` invocation: `$ARGUMENTS` becomes `args`, placeholders keep
+/// their default text, and arguments are appended when the template has no `$ARGUMENTS`
+pub fn expand_invocation(snippet: &Snippet, args: &str, cwd: Option<&str>) -> String {
+    let (body, _) = parse_placeholders(&snippet.body);
+    let body = expand_variables(&body, cwd);
+    if body.contains("$ARGUMENTS") {
+        body.replace("$ARGUMENTS", args)
+    } else if args.is_empty() {
+        body
+    } else {
+        format!("{}\n\n{}", body, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_placeholders() {
+        let (text, tabstops) = parse_placeholders("${2:理由}で${1:対象ファイル}を修正${0} ${x}");
+        assert_eq!(text, "理由で対象ファイルを修正 ${x}");
+        let order: Vec<u32> = tabstops.iter().map(|t| t.index).collect();
+        assert_eq!(order, vec![1, 2, 0]);
+        assert_eq!(&text[tabstops[0].start..tabstops[0].end], "対象ファイル");
+        assert_eq!(tabstops[2].start, tabstops[2].end);
+
+        // Variables are substituted after parsing: their text never becomes a placeholder
+        let snippet = Snippet {
+            name: "cwd".to_string(),
+            description: String::new(),
+            body: "{cwd}: ${1:対象}".to_string(),
+        };
+        let (text, tabstops) = expand(&snippet, Some("${2:x}"));
+        assert_eq!(text, "${2:x}: 対象");
+        assert_eq!(tabstops.len(), 1);
+        assert_eq!(&text[tabstops[0].start..tabstops[0].end], "対象");
+    }
+
+    #[test]
+    fn test_expand_invocation() {
+        let snippet = Snippet {
+            name: "bug".to_string(),
+            description: String::new(),
+            body: "${1:対象}の不具合: $ARGUMENTS".to_string(),
+        };
+        assert_eq!(expand_invocation(&snippet, "ログインできない", None), "対象の不具合: ログインできない");
+        assert_eq!(expand_invocation(&snippet, "${1:x} が出る", None), "対象の不具合: ${1:x} が出る");
+    }
+}
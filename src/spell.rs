@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::lint::{Issue, Rule};
+use crate::logger;
+use crate::normalize;
+use crate::paths;
+
+/// Suggestions offered for a misspelled word
+const MAX_SUGGESTIONS: usize = 6;
+
+/// Spellcheck settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpellcheckConfig {
+    pub enabled: bool,
+    /// Hunspell dictionary path without extension (`.../en_US` for `en_US.dic` and `en_US.aff`)
+    pub dictionary: Option<String>,
+}
+
+impl Default for SpellcheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dictionary: None,
+        }
+    }
+}
+
+impl SpellcheckConfig {
+    /// The configured dictionary, or the first `en_US` found in the usual places
+    pub fn dictionary_path(&self) -> Option<PathBuf> {
+        if let Some(ref path) = self.dictionary {
+            return Some(PathBuf::from(path));
+        }
+        let mut candidates = vec![paths::data_dir().join("dictionaries").join("en_US")];
+        if !cfg!(windows) {
+            candidates.push(PathBuf::from("/usr/share/hunspell/en_US"));
+        }
+        candidates.into_iter().find(|c| c.with_extension("dic").exists())
+    }
+}
+
+/// An affix flag (one character, two characters with `FLAG long`, or a number with `FLAG num`)
+type Flag = u32;
+
+#[derive(Debug, Clone, Copy)]
+enum FlagMode {
+    Char,
+    Long,
+    Num,
+}
+
+/// One character position of an affix condition
+#[derive(Debug)]
+enum Condition {
+    Any,
+    Char(char),
+    Set(Vec<char>, bool), // characters, negated
+}
+
+impl Condition {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Char(expected) => c == *expected,
+            Self::Set(chars, negated) => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+/// A PFX/SFX rule: remove `strip`, add `add`, where the stem matches `condition`
+#[derive(Debug)]
+struct Affix {
+    flag: Flag,
+    cross_product: bool,
+    strip: String,
+    add: String,
+    condition: Vec<Condition>,
+}
+
+/// A Hunspell dictionary (words with their affix flags, and the affix rules)
+#[derive(Debug)]
+pub struct Dictionary {
+    words: HashMap<String, Vec<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    /// Letters tried when suggesting, most common first
+    try_chars: Vec<char>,
+}
+
+fn parse_flags(flags: &str, mode: FlagMode) -> Vec<Flag> {
+    match mode {
+        FlagMode::Char => flags.chars().map(|c| c as Flag).collect(),
+        FlagMode::Long => {
+            let chars: Vec<char> = flags.chars().collect();
+            chars.chunks(2).map(|pair| pair.iter().fold(0, |flag, &c| (flag << 16) | c as Flag)).collect()
+        }
+        FlagMode::Num => flags.split(',').filter_map(|n| n.trim().parse().ok()).collect(),
+    }
+}
+
+fn parse_condition(condition: &str) -> Vec<Condition> {
+    let mut parsed = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        parsed.push(match c {
+            '.' => Condition::Any,
+            '[' => {
+                let set: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                match set.strip_prefix('^') {
+                    Some(set) => Condition::Set(set.chars().collect(), true),
+                    None => Condition::Set(set.chars().collect(), false),
+                }
+            }
+            c => Condition::Char(c),
+        });
+    }
+    parsed
+}
+
+impl Dictionary {
+    /// Parse the contents of a `.aff` and a `.dic` file
+    pub fn parse(aff: &str, dic: &str) -> Self {
+        let mut mode = FlagMode::Char;
+        let mut prefixes = Vec::new();
+        let mut suffixes = Vec::new();
+        let mut try_chars = Vec::new();
+        // Cross-product setting from each PFX/SFX header
+        let mut headers: HashMap<(bool, Flag), bool> = HashMap::new();
+
+        for line in aff.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                ["FLAG", "long", ..] => mode = FlagMode::Long,
+                ["FLAG", "num", ..] => mode = FlagMode::Num,
+                ["TRY", letters, ..] => try_chars = letters.chars().collect(),
+                [kind @ ("PFX" | "SFX"), flag, rest @ ..] => {
+                    let is_prefix = *kind == "PFX";
+                    let Some(&flag) = parse_flags(flag, mode).first() else {
+                        continue;
+                    };
+                    match rest {
+                        [cross, count] if !headers.contains_key(&(is_prefix, flag)) && count.parse::<usize>().is_ok() => {
+                            headers.insert((is_prefix, flag), *cross == "Y");
+                        }
+                        [strip, add, condition @ ..] => {
+                            let add = add.split('/').next().unwrap_or_default();
+                            let affix = Affix {
+                                flag,
+                                cross_product: headers.get(&(is_prefix, flag)).copied().unwrap_or(false),
+                                strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                                add: if add == "0" { String::new() } else { add.to_string() },
+                                condition: parse_condition(condition.first().copied().unwrap_or(".")),
+                            };
+                            if is_prefix {
+                                prefixes.push(affix);
+                            } else {
+                                suffixes.push(affix);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The first line of a .dic is the word count
+        let words = dic
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let entry = line.split_whitespace().next()?;
+                let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+                Some((word.to_string(), parse_flags(flags, mode)))
+            })
+            .collect();
+
+        Self {
+            words,
+            prefixes,
+            suffixes,
+            try_chars,
+        }
+    }
+
+    /// Read `<path>.aff` and `<path>.dic` (non-UTF-8 dictionaries are read lossily)
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let read = |extension: &str| {
+            let file = path.with_extension(extension);
+            std::fs::read(&file)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .map_err(|e| format!("Failed to read {}: {}", file.display(), e))
+        };
+        Ok(Self::parse(&read("aff")?, &read("dic")?))
+    }
+
+    fn has_flag(&self, word: &str, flag: Flag) -> bool {
+        self.words.get(word).is_some_and(|flags| flags.contains(&flag))
+    }
+
+    /// Stems `word` could come from by removing `affix` (suffix or prefix)
+    fn strip_affix(word: &str, affix: &Affix, is_prefix: bool) -> Option<String> {
+        let stem = if is_prefix {
+            format!("{}{}", affix.strip, word.strip_prefix(affix.add.as_str())?)
+        } else {
+            format!("{}{}", word.strip_suffix(affix.add.as_str())?, affix.strip)
+        };
+        if stem.is_empty() {
+            return None;
+        }
+        let chars: Vec<char> = stem.chars().collect();
+        if chars.len() < affix.condition.len() {
+            return None;
+        }
+        let matches = if is_prefix {
+            affix.condition.iter().zip(&chars).all(|(cond, &c)| cond.matches(c))
+        } else {
+            affix.condition.iter().rev().zip(chars.iter().rev()).all(|(cond, &c)| cond.matches(c))
+        };
+        matches.then_some(stem)
+    }
+
+    fn check_form(&self, word: &str) -> bool {
+        if self.words.contains_key(word) {
+            return true;
+        }
+        let suffix_stem = |word: &str, also: Option<Flag>| {
+            self.suffixes.iter().any(|sfx| {
+                (also.is_none() || sfx.cross_product)
+                    && Self::strip_affix(word, sfx, false).is_some_and(|stem| {
+                        self.has_flag(&stem, sfx.flag) && also.is_none_or(|flag| self.has_flag(&stem, flag))
+                    })
+            })
+        };
+        if suffix_stem(word, None) {
+            return true;
+        }
+        self.prefixes.iter().any(|pfx| {
+            Self::strip_affix(word, pfx, true).is_some_and(|stem| {
+                self.has_flag(&stem, pfx.flag) || (pfx.cross_product && suffix_stem(&stem, Some(pfx.flag)))
+            })
+        })
+    }
+
+    /// Whether the dictionary accepts `word` (also lowercased when capitalized or all caps)
+    pub fn check(&self, word: &str) -> bool {
+        if self.check_form(word) {
+            return true;
+        }
+        let lower = word.to_lowercase();
+        lower != word && self.check_form(&lower)
+    }
+
+    /// Correctly spelled words one edit away, keeping the word's capitalization
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let lower: Vec<char> = word.to_lowercase().chars().collect();
+        let letters: Vec<char> = if self.try_chars.is_empty() {
+            ('a'..='z').collect()
+        } else {
+            self.try_chars.iter().copied().filter(char::is_ascii_lowercase).collect()
+        };
+
+        let mut candidates: Vec<Vec<char>> = Vec::new();
+        for i in 0..lower.len().saturating_sub(1) {
+            let mut swapped = lower.clone();
+            swapped.swap(i, i + 1);
+            candidates.push(swapped);
+        }
+        for i in 0..lower.len() {
+            for &c in &letters {
+                let mut replaced = lower.clone();
+                replaced[i] = c;
+                candidates.push(replaced);
+            }
+        }
+        for i in 0..lower.len() {
+            let mut deleted = lower.clone();
+            deleted.remove(i);
+            candidates.push(deleted);
+        }
+        for i in 0..=lower.len() {
+            for &c in &letters {
+                let mut inserted = lower.clone();
+                inserted.insert(i, c);
+                candidates.push(inserted);
+            }
+        }
+
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        let mut suggestions: Vec<String> = Vec::new();
+        for candidate in candidates {
+            let mut candidate: String = candidate.into_iter().collect();
+            if candidate == word.to_lowercase() || !self.check_form(&candidate) {
+                continue;
+            }
+            if capitalized {
+                candidate = capitalize(&candidate);
+            }
+            if !suggestions.contains(&candidate) {
+                suggestions.push(candidate);
+            }
+            if suggestions.len() >= MAX_SUGGESTIONS {
+                break;
+            }
+        }
+        suggestions
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// Load the dictionary on a worker thread (the slot stays empty if it can't be read)
+pub fn spawn_loader(path: Option<PathBuf>) -> Arc<OnceLock<Dictionary>> {
+    let dictionary = Arc::new(OnceLock::new());
+    if let Some(path) = path {
+        let slot = Arc::clone(&dictionary);
+        std::thread::spawn(move || match Dictionary::load(&path) {
+            Ok(loaded) => {
+                logger::log(&format!("[DEBUG spell] Loaded {} words from {}", loaded.words.len(), path.display()));
+                let _ = slot.set(loaded);
+            }
+            Err(e) => logger::log(&format!("[DEBUG spell] {}", e)),
+        });
+    }
+    dictionary
+}
+
+/// Lowercase words making up identifiers (`parseConfig`, `read_json` -> parse, config, read, json)
+pub fn harvest_words<'a>(identifiers: impl Iterator<Item = &'a String>) -> HashSet<String> {
+    let mut words = HashSet::new();
+    for identifier in identifiers {
+        let mut word = String::new();
+        let mut previous_lower = false;
+        for c in identifier.chars().chain(std::iter::once('_')) {
+            let boundary = !c.is_ascii_alphabetic() || (c.is_ascii_uppercase() && previous_lower);
+            if boundary && word.len() >= 2 {
+                words.insert(std::mem::take(&mut word));
+            }
+            if boundary {
+                word.clear();
+            }
+            if c.is_ascii_alphabetic() {
+                word.push(c.to_ascii_lowercase());
+            }
+            previous_lower = c.is_ascii_lowercase();
+        }
+    }
+    words
+}
+
+/// Latin-script words outside code that aren't in the dictionary or the project's identifiers
+/// Paths, identifiers (`snake_case`, `camelCase`, `ALLCAPS`) and URLs are skipped
+pub fn misspellings(text: &str, dictionary: &Dictionary, project_words: &HashSet<String>) -> Vec<Issue> {
+    let masked = normalize::mask_code(text);
+    let mut issues = Vec::new();
+
+    for (line_index, line) in masked.split('\n').enumerate() {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let char_at = |i: usize| chars.get(i).map(|(_, c)| *c);
+        let mut i = 0;
+        while i < chars.len() {
+            if !chars[i].1.is_ascii_alphabetic() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while char_at(i).is_some_and(|c| c.is_ascii_alphabetic() || c == '\'') {
+                i += 1;
+            }
+            // Don't count an apostrophe at the end (`users'`)
+            let mut end = i;
+            while end > start && chars[end - 1].1 == '\'' {
+                end -= 1;
+            }
+
+            let before = start.checked_sub(1).and_then(char_at);
+            let after = char_at(i);
+            let glued = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit() || "_/\\@#$:(".contains(c));
+            let dotted = |c: Option<char>, next: Option<char>| c == Some('.') && next.is_some_and(|n| n.is_ascii_alphanumeric());
+            let in_path = glued(before)
+                || glued(after)
+                || dotted(after, char_at(i + 1))
+                || dotted(before, start.checked_sub(2).and_then(char_at));
+
+            let word: String = chars[start..end].iter().map(|(_, c)| c).collect();
+            let identifier_like = word.chars().skip(1).any(|c| c.is_ascii_uppercase());
+            let skip = in_path || identifier_like || word.len() < 2 || project_words.contains(&word.to_lowercase());
+            if !skip && !dictionary.check(&word) {
+                let byte_end = chars.get(end).map_or(line.len(), |(b, _)| *b);
+                issues.push(Issue {
+                    rule: Rule::Spelling,
+                    line: line_index,
+                    range: chars[start].0..byte_end,
+                    message: format!("スペル: {}", word),
+                    fix: None,
+                });
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictionary() {
+        let aff = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwz\n\
+                   SFX D Y 2\nSFX D 0 d e\nSFX D 0 ed [^e]\n\
+                   SFX S Y 1\nSFX S 0 s .\n\
+                   PFX U Y 1\nPFX U 0 un .";
+        let dic = "3\nreceive/DS\nseparate/DS\nlock/DU";
+        let dictionary = Dictionary::parse(aff, dic);
+
+        assert!(dictionary.check("received"));
+        assert!(dictionary.check("Separates"));
+        assert!(dictionary.check("unlocked"));
+        assert!(!dictionary.check("recieve"));
+        assert_eq!(dictionary.suggest("recieve"), vec!["receive"]);
+        assert_eq!(dictionary.suggest("Seperate"), vec!["Separate"]);
+
+        let project = harvest_words(["parseTokio".to_string(), "serde_json".to_string()].iter());
+        let issues = misspellings("recieve を tokio で `seperate` src/recieve.rs", &dictionary, &project);
+        let words: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(words, vec!["スペル: recieve"]);
+    }
+}
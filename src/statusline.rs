@@ -0,0 +1,210 @@
+use serde::Deserialize;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use crate::ipc::{self, StatusLineInfo};
+use crate::logger;
+
+/// Context window size assumed when Claude Code doesn't report one
+const DEFAULT_CONTEXT_WINDOW: u64 = 200_000;
+
+/// Maximum stdin size (same limit as hook input)
+const MAX_INPUT_SIZE: usize = 100 * 1024;
+
+/// How much of the transcript's end is read first (doubled until a usage entry is found)
+const TRANSCRIPT_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Input of Claude Code's statusLine command (only the fields we display)
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StatusLineInput {
+    pub session_id: String,
+    pub transcript_path: String,
+    pub cwd: String,
+    pub model: Model,
+    pub workspace: Workspace,
+    pub cost: Cost,
+    pub context_window: Option<ContextWindow>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Model {
+    pub display_name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub current_dir: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Cost {
+    pub total_cost_usd: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ContextWindow {
+    pub context_window_size: Option<u64>,
+    pub current_usage: Option<Usage>,
+}
+
+/// Token usage as reported by the API (also found in transcript entries)
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    fn context_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+}
+
+/// Context usage from the last assistant message in the transcript (JSONL)
+fn usage_from_transcript(path: &str) -> Option<u64> {
+    #[derive(Deserialize)]
+    struct Entry {
+        message: Option<Message>,
+    }
+    #[derive(Deserialize)]
+    struct Message {
+        usage: Option<Usage>,
+    }
+
+    // The statusline runs after every message, so read from the end instead of the whole file
+    let mut file = std::fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut tail = TRANSCRIPT_TAIL_BYTES;
+    loop {
+        let start = len.saturating_sub(tail);
+        file.seek(SeekFrom::Start(start)).ok()?;
+        let mut bytes = Vec::new();
+        file.by_ref().take(len - start).read_to_end(&mut bytes).ok()?;
+        let text = String::from_utf8_lossy(&bytes);
+        let mut lines = text.lines();
+        if start > 0 {
+            lines.next(); // Partial line
+        }
+        let usage = lines
+            .rev()
+            .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+            .find_map(|entry| entry.message?.usage);
+        if let Some(usage) = usage {
+            return Some(usage.context_tokens());
+        }
+        if start == 0 {
+            return None;
+        }
+        tail *= 2;
+    }
+}
+
+/// Last component of a path, for display (`C:\Users\me\my-project` -> `my-project`)
+pub fn dir_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).find(|s| !s.is_empty()).unwrap_or(path)
+}
+
+/// Build the info shown in the statusline and the resident footer
+pub fn status_info(input: &StatusLineInput) -> StatusLineInfo {
+    let context_window = input.context_window.as_ref();
+    let context_size = context_window
+        .and_then(|c| c.context_window_size)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW);
+    let context_tokens = context_window
+        .and_then(|c| c.current_usage.as_ref())
+        .map(Usage::context_tokens)
+        .or_else(|| usage_from_transcript(&input.transcript_path));
+
+    let cwd = if input.workspace.current_dir.is_empty() {
+        input.cwd.clone()
+    } else {
+        input.workspace.current_dir.clone()
+    };
+
+    StatusLineInfo {
+        model: input.model.display_name.clone(),
+        cwd,
+        cost_usd: input.cost.total_cost_usd,
+        context_percent: context_tokens.map(|t| (t * 100 / context_size.max(1)).min(100) as u8),
+    }
+}
+
+/// Single-line rendering: `Opus | my-project | $0.42 | ctx 37%`
+pub fn format_line(info: &StatusLineInfo) -> String {
+    let mut parts = vec![info.model.clone(), dir_name(&info.cwd).to_string(), format!("${:.2}", info.cost_usd)];
+    if let Some(percent) = info.context_percent {
+        parts.push(format!("ctx {}%", percent));
+    }
+    parts.retain(|p| !p.is_empty());
+    parts.join(" | ")
+}
+
+/// Run `moji-bridge statusline`: print the line and mirror it to the resident window
+pub fn run() -> Result<(), String> {
+    let mut json = String::new();
+    io::stdin()
+        .lock()
+        .take(MAX_INPUT_SIZE as u64)
+        .read_to_string(&mut json)
+        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    let input: StatusLineInput =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+    let info = status_info(&input);
+    println!("{}", format_line(&info));
+
+    // Best effort: the statusline must never fail because the window is gone
+    if let Some(instance) = ipc::find_instance_for_session(&input.session_id) {
+        let request = ipc::Request::StatusLine {
+            session_id: input.session_id.clone(),
+            info,
+        };
+        if let Err(e) = ipc::send(&instance, &request, Some(Duration::from_millis(500))) {
+            logger::log(&format!("[DEBUG statusline] {}", e));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_info() {
+        let json = r#"{
+            "session_id": "abc",
+            "model": {"id": "claude-opus", "display_name": "Opus"},
+            "workspace": {"current_dir": "C:\\Users\\me\\my-project"},
+            "cost": {"total_cost_usd": 0.4216},
+            "context_window": {
+                "context_window_size": 200000,
+                "current_usage": {"input_tokens": 10000, "cache_read_input_tokens": 64000}
+            }
+        }"#;
+        let input: StatusLineInput = serde_json::from_str(json).unwrap();
+        let info = status_info(&input);
+        assert_eq!(info.context_percent, Some(37));
+        assert_eq!(format_line(&info), "Opus | my-project | $0.42 | ctx 37%");
+
+        // Without current_usage, the last usage in the transcript counts
+        let path = std::env::temp_dir().join(format!("moji-bridge-test-{}.jsonl", std::process::id()));
+        let early = r#"{"message":{"usage":{"input_tokens":1000}}}"#;
+        let last = r#"{"message":{"usage":{"input_tokens":50000}}}"#;
+        let filler = format!(r#"{{"type":"user","text":"{}"}}"#, "x".repeat(1000));
+        let lines: Vec<&str> = std::iter::once(early)
+            .chain(std::iter::once(last))
+            .chain(std::iter::repeat_n(filler.as_str(), 200))
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        assert_eq!(usage_from_transcript(&path.to_string_lossy()), Some(50000));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -212,6 +212,17 @@ pub fn get_terminal_pid() -> Option<u32> {
     TERMINAL_PID.get().copied()
 }
 
+/// Resolve the terminal window: the given hwnd, or the window of the tracked terminal process
+fn resolve_terminal_hwnd(hwnd_override: Option<isize>) -> Result<isize, String> {
+    if let Some(h) = hwnd_override {
+        return Ok(h);
+    }
+    let terminal_pid = get_terminal_pid()
+        .ok_or("Terminal process not found. Was init_terminal_tracking() called?")?;
+    get_window_by_pid(terminal_pid)
+        .ok_or(format!("Could not find window for terminal PID {}", terminal_pid))
+}
+
 /// Send trigger input to the terminal
 /// This function:
 /// 1. Gets the terminal window handle (from override or by finding terminal process)
@@ -221,15 +232,7 @@ pub fn get_terminal_pid() -> Option<u32> {
 pub fn send_to_terminal(hwnd_override: Option<isize>) -> Result<(), String> {
     logger::log(&format!("[DEBUG terminal] send_to_terminal received hwnd_override: {:?}", hwnd_override));
     // Use provided hwnd if available, otherwise fall back to PID-based lookup
-    let hwnd = if let Some(h) = hwnd_override {
-        h
-    } else {
-        // Fallback: Get terminal PID and find its window
-        let terminal_pid = get_terminal_pid()
-            .ok_or("Terminal process not found. Was init_terminal_tracking() called?")?;
-        get_window_by_pid(terminal_pid)
-            .ok_or(format!("Could not find window for terminal PID {}", terminal_pid))?
-    };
+    let hwnd = resolve_terminal_hwnd(hwnd_override)?;
 
     logger::log(&format!("[DEBUG terminal] Using hwnd: {}", hwnd));
 
@@ -275,14 +278,7 @@ pub fn paste_to_terminal(hwnd_override: Option<isize>) -> Result<(), String> {
     logger::log(&format!("[DEBUG terminal] paste_to_terminal received hwnd_override: {:?}", hwnd_override));
 
     // Use provided hwnd if available, otherwise fall back to PID-based lookup
-    let hwnd = if let Some(h) = hwnd_override {
-        h
-    } else {
-        let terminal_pid = get_terminal_pid()
-            .ok_or("Terminal process not found. Was init_terminal_tracking() called?")?;
-        get_window_by_pid(terminal_pid)
-            .ok_or(format!("Could not find window for terminal PID {}", terminal_pid))?
-    };
+    let hwnd = resolve_terminal_hwnd(hwnd_override)?;
 
     logger::log(&format!("[DEBUG terminal] paste_to_terminal using hwnd: {}", hwnd));
 
@@ -324,6 +320,107 @@ pub fn paste_to_terminal(hwnd_override: Option<isize>) -> Result<(), String> {
     Ok(())
 }
 
+/// Single keystrokes that can be forwarded to Claude's interactive menus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardKey {
+    Escape,
+    ShiftTab,
+    Up,
+    Down,
+    Enter,
+    Digit(u8),
+}
+
+impl ForwardKey {
+    /// Label for the forwarding toolbar
+    pub fn label(self) -> String {
+        match self {
+            ForwardKey::Escape => "Esc".to_string(),
+            ForwardKey::ShiftTab => "Shift+Tab".to_string(),
+            ForwardKey::Up => "↑".to_string(),
+            ForwardKey::Down => "↓".to_string(),
+            ForwardKey::Enter => "Enter".to_string(),
+            ForwardKey::Digit(d) => d.to_string(),
+        }
+    }
+}
+
+/// How long to wait for the user to let go of Alt before forwarding a key
+#[cfg(windows)]
+const ALT_RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Wait until Alt is no longer held (the terminal would otherwise get Alt+1, Alt+Enter, ...)
+#[cfg(windows)]
+fn wait_for_alt_release() -> bool {
+    use std::time::Instant;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_MENU};
+
+    let started = Instant::now();
+    while unsafe { GetAsyncKeyState(VK_MENU.0 as i32) } < 0 {
+        if started.elapsed() >= ALT_RELEASE_TIMEOUT {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
+
+#[cfg(not(windows))]
+fn wait_for_alt_release() -> bool {
+    true
+}
+
+/// Send a single keystroke to the terminal
+/// Focuses the terminal and presses the key; the caller is responsible for refocusing
+/// Blocks for a moment (Alt release, focus change), so run it off the UI thread
+pub fn send_key_to_terminal(hwnd_override: Option<isize>, key: ForwardKey) -> Result<(), String> {
+    let hwnd = resolve_terminal_hwnd(hwnd_override)?;
+    logger::log(&format!("[DEBUG terminal] send_key_to_terminal {:?} to hwnd: {}", key, hwnd));
+
+    // The key is forwarded with Alt+key, so it must not reach the terminal while Alt is down
+    if !wait_for_alt_release() {
+        return Err("Release Alt to send the key".to_string());
+    }
+
+    if !set_foreground_window(hwnd) {
+        return Err("Failed to set foreground window".to_string());
+    }
+
+    // Wait for window to become active
+    thread::sleep(Duration::from_millis(150));
+
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| format!("Failed to create Enigo instance: {}", e))?;
+
+    let press = |enigo: &mut Enigo, key: Key| {
+        enigo.key(key, enigo::Direction::Click)
+            .map_err(|e| format!("Failed to press {:?}: {}", key, e))
+    };
+    match key {
+        ForwardKey::Escape => press(&mut enigo, Key::Escape)?,
+        ForwardKey::Up => press(&mut enigo, Key::UpArrow)?,
+        ForwardKey::Down => press(&mut enigo, Key::DownArrow)?,
+        ForwardKey::Enter => press(&mut enigo, Key::Return)?,
+        ForwardKey::Digit(d) => {
+            let c = char::from_digit(d as u32, 10).ok_or("Invalid digit")?;
+            press(&mut enigo, Key::Unicode(c))?;
+        }
+        ForwardKey::ShiftTab => {
+            enigo.key(Key::Shift, enigo::Direction::Press)
+                .map_err(|e| format!("Failed to press Shift: {}", e))?;
+            // Release Shift even if Tab fails, so it doesn't stay stuck
+            let tab = press(&mut enigo, Key::Tab);
+            let release = enigo.key(Key::Shift, enigo::Direction::Release)
+                .map_err(|e| format!("Failed to release Shift: {}", e));
+            tab.and(release)?;
+        }
+    }
+
+    // Give the terminal a moment to process the key before focus moves back
+    thread::sleep(Duration::from_millis(50));
+    Ok(())
+}
+
 // Keep old function for backward compatibility
 #[cfg(windows)]
 pub fn get_foreground_window() -> Option<isize> {
@@ -397,6 +494,7 @@ pub fn start_terminal_monitor(terminal_hwnd: isize) {
                 let hwnd = HWND(terminal_hwnd as *mut std::ffi::c_void);
                 if !IsWindow(hwnd).as_bool() {
                     logger::log("[DEBUG terminal] Terminal window closed, exiting MojiBridge");
+                    crate::ipc::unregister_instance(terminal_hwnd);
                     std::process::exit(0);
                 }
             }
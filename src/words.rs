@@ -0,0 +1,90 @@
+use icu_segmenter::options::WordBreakInvariantOptions;
+use icu_segmenter::WordSegmenter;
+use std::ops::Range;
+
+/// Kana, kanji and Japanese punctuation (the text the editor's own word motions handle badly)
+pub fn is_japanese(c: char) -> bool {
+    matches!(c,
+        '\u{3001}'..='\u{303F}' // 、。「」 and iteration marks
+        | '\u{3041}'..='\u{30FF}' // Hiragana, katakana, ー
+        | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' // Kanji
+        | '\u{FF01}'..='\u{FF60}' | '\u{FF61}'..='\u{FF9F}' // Full-width forms, half-width katakana
+    ) && c != '\u{3000}'
+}
+
+/// The run of Japanese text containing the character starting at byte `index`
+fn run_at(line: &str, index: usize) -> Option<Range<usize>> {
+    let c = line.get(index..)?.chars().next()?;
+    if !is_japanese(c) {
+        return None;
+    }
+    let start = line[..index]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_japanese(*c))
+        .last()
+        .map_or(index, |(i, _)| i);
+    let end = line[index..]
+        .char_indices()
+        .find(|(_, c)| !is_japanese(*c))
+        .map_or(line.len(), |(i, _)| index + i);
+    Some(start..end)
+}
+
+/// Word boundaries within a Japanese run, from the embedded dictionary segmenter
+fn boundaries(line: &str, run: Range<usize>) -> Vec<usize> {
+    let segmenter = WordSegmenter::new_dictionary(WordBreakInvariantOptions::default());
+    segmenter.segment_str(&line[run.clone()]).map(|i| run.start + i).collect()
+}
+
+/// Byte index of the character before `column`
+fn previous_char(line: &str, column: usize) -> Option<usize> {
+    line.get(..column)?.char_indices().next_back().map(|(i, _)| i)
+}
+
+/// Where Ctrl+Left goes from `column`, or None when the text before it isn't Japanese
+pub fn previous_boundary(line: &str, column: usize) -> Option<usize> {
+    let run = run_at(line, previous_char(line, column)?)?;
+    boundaries(line, run).into_iter().rev().find(|&b| b < column)
+}
+
+/// Where Ctrl+Right goes from `column`, or None when the text after it isn't Japanese
+pub fn next_boundary(line: &str, column: usize) -> Option<usize> {
+    let run = run_at(line, column)?;
+    boundaries(line, run).into_iter().find(|&b| b > column)
+}
+
+/// The Japanese word at `column` (for double-click), or None outside Japanese text
+pub fn word_at(line: &str, column: usize) -> Option<Range<usize>> {
+    // At the end of a run (clicked right after the last character), take the word before
+    let index = if run_at(line, column).is_some() { column } else { previous_char(line, column)? };
+    let run = run_at(line, index)?;
+    let bounds = boundaries(line, run);
+    let start = bounds.iter().rev().find(|&&b| b <= index).copied()?;
+    let end = bounds.iter().find(|&&b| b > index).copied()?;
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_boundaries() {
+        let line = "let x = 1; 今日は良い天気です。";
+        let start = line.find('今').unwrap_or_default();
+        let words: Vec<&str> = std::iter::successors(Some(start), |&i| next_boundary(line, i))
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|w| &line[w[0]..w[1]])
+            .collect();
+        assert_eq!(words, vec!["今日", "は", "良い", "天気", "です", "。"]);
+
+        let end = line.len();
+        assert_eq!(previous_boundary(line, end), Some(end - "。".len()));
+        assert_eq!(word_at(line, start + "今日は".len()).map(|r| &line[r]), Some("良い"));
+        // Latin text is left to the editor
+        assert_eq!(next_boundary(line, 0), None);
+        assert_eq!(word_at(line, 1), None);
+    }
+}